@@ -0,0 +1,351 @@
+use clap::{Parser, Subcommand, ValueEnum};
+#[cfg(target_os = "linux")]
+use nix::sys::termios::BaudRate;
+use rpi_controller::rf24::{PowerLevel, SpiConfig};
+#[cfg(target_os = "linux")]
+use rpi_controller::serial::BAUD_RATES;
+use rppal::spi;
+#[cfg(feature = "mqtt")]
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(about = "Sends the gamepad input to the robot through the NRF24L01+ module")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Where the gamepad input is read from.
+    #[arg(long, value_enum, default_value_t = InputBackend::DEFAULT)]
+    pub input: InputBackend,
+
+    /// Script of timed gamepad events, used with `--input script`.
+    #[arg(long, required_if_eq("input", "script"))]
+    pub script: Option<PathBuf>,
+
+    /// Address and port the gamepad page is served on, used with `--input web`.
+    #[arg(long, default_value = "0.0.0.0:8080")]
+    pub web_address: String,
+
+    /// Records every frame sent to the robot into this file.
+    #[arg(long)]
+    pub record: Option<PathBuf>,
+
+    /// Asks the robot to acknowledge each frame with its telemetry.
+    ///
+    /// The robot firmware must be built with `RADIO_TELEMETRY` enabled.
+    #[arg(long)]
+    pub telemetry: bool,
+
+    /// Steers by tilting the gamepad, on gamepads with motion sensors (SDL input only).
+    ///
+    /// Pressing the left stick takes the current position as straight ahead.
+    #[cfg(feature = "sdl2")]
+    #[arg(long)]
+    pub tilt: bool,
+
+    /// Moves to the quietest channel at startup, and to the next quietest when packets get lost.
+    ///
+    /// The robot is told to follow. Moving on loss needs `--telemetry`.
+    #[arg(long)]
+    pub auto_channel: bool,
+
+    /// Hops through a channel sequence generated from this seed when packets get lost.
+    ///
+    /// The robot is told to follow. Hopping needs `--telemetry`, without it only the first
+    /// channel of the sequence is used.
+    #[arg(long, conflicts_with = "auto_channel")]
+    pub hop_seed: Option<u64>,
+
+    /// Saves power while the gamepad is idle, for controllers running on batteries.
+    ///
+    /// After 5s without input only a keep-alive is sent every 500ms, and after a minute the
+    /// radio is powered down, which the robot sees as a lost link.
+    #[arg(long)]
+    pub power_save: bool,
+
+    /// Shows the input, the radio and the robot live in the terminal.
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Sends the frames over UDP to a gateway next to the robot, instead of the local radio.
+    ///
+    /// The port defaults to 7870. Only driving and replaying work through a gateway.
+    #[arg(
+        long,
+        value_name = "HOST[:PORT]",
+        conflicts_with_all = ["tui", "auto_channel", "hop_seed", "listener"]
+    )]
+    pub gateway: Option<String>,
+
+    /// Sends the frames through an nRF24 dongle on this serial port, instead of the local radio.
+    ///
+    /// Only driving and replaying work through a dongle.
+    #[cfg(target_os = "linux")]
+    #[arg(
+        long,
+        value_name = "PORT",
+        conflicts_with_all = ["gateway", "tui", "auto_channel", "hop_seed", "listener"]
+    )]
+    pub serial: Option<PathBuf>,
+
+    /// Speed of the serial port of the dongle.
+    #[cfg(target_os = "linux")]
+    #[arg(long, default_value = "115200", value_parser = parse_baud_rate)]
+    pub baud: BaudRate,
+
+    /// Publishes the session to this MQTT broker and takes remote commands from it.
+    ///
+    /// The port defaults to 1883. The connection and the commands are printed, which the
+    /// dashboard has no room for.
+    #[cfg(feature = "mqtt")]
+    #[arg(
+        long,
+        value_name = "HOST[:PORT]",
+        value_parser = parse_broker,
+        conflicts_with = "tui"
+    )]
+    pub mqtt: Option<(String, u16)>,
+
+    /// Prefix of the MQTT topics.
+    #[cfg(feature = "mqtt")]
+    #[arg(long, default_value = "robotoy")]
+    pub mqtt_prefix: String,
+
+    /// SPI bus the radio is on, from 0 to 6.
+    #[arg(long, default_value = "0", value_parser = parse_spi_bus, help_heading = "Radio wiring")]
+    pub spi_bus: spi::Bus,
+
+    /// Slave select line of the bus the radio is on, from 0 to 2.
+    #[arg(long, default_value = "0", value_parser = parse_slave_select, help_heading = "Radio wiring")]
+    pub slave_select: spi::SlaveSelect,
+
+    /// SPI clock in Hz, up to 10MHz.
+    #[arg(long, default_value_t = 10_000_000, help_heading = "Radio wiring")]
+    pub spi_clock: u32,
+
+    /// GPIO used as chip select instead of the slave select line, which must then be left
+    /// unconnected.
+    #[arg(long, help_heading = "Radio wiring")]
+    pub cs_pin: Option<u8>,
+
+    /// GPIO wired to the CE pin of the radio.
+    #[arg(long, default_value_t = 25, help_heading = "Radio wiring")]
+    pub ce_pin: u8,
+
+    /// Listens with a second radio while the first one sends, to see the frames go out and
+    /// the telemetry come back.
+    #[arg(long, help_heading = "Second radio")]
+    pub listener: bool,
+
+    /// SPI bus the second radio is on, from 0 to 6.
+    #[arg(long, default_value = "0", value_parser = parse_spi_bus, help_heading = "Second radio")]
+    pub listener_spi_bus: spi::Bus,
+
+    /// Slave select line of the bus the second radio is on, from 0 to 2.
+    #[arg(long, default_value = "1", value_parser = parse_slave_select, help_heading = "Second radio")]
+    pub listener_slave_select: spi::SlaveSelect,
+
+    /// GPIO used as chip select of the second radio instead of the slave select line.
+    #[arg(long, help_heading = "Second radio")]
+    pub listener_cs_pin: Option<u8>,
+
+    /// GPIO wired to the CE pin of the second radio.
+    #[arg(long, default_value_t = 24, help_heading = "Second radio")]
+    pub listener_ce_pin: u8,
+}
+
+impl Cli {
+    /// Whether the frames go through a gateway or a dongle, instead of the local radio.
+    pub fn remote(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if self.serial.is_some() {
+            return true;
+        }
+        self.gateway.is_some()
+    }
+
+    pub fn spi_config(&self) -> SpiConfig {
+        SpiConfig {
+            bus: self.spi_bus,
+            slave_select: self.slave_select,
+            clock_speed: self.spi_clock,
+            cs_pin: self.cs_pin,
+            ce_pin: self.ce_pin,
+        }
+    }
+
+    /// The wiring of the second radio, with the SPI clock of the first.
+    pub fn listener_spi_config(&self) -> SpiConfig {
+        SpiConfig {
+            bus: self.listener_spi_bus,
+            slave_select: self.listener_slave_select,
+            clock_speed: self.spi_clock,
+            cs_pin: self.listener_cs_pin,
+            ce_pin: self.listener_ce_pin,
+        }
+    }
+}
+
+/// An IPv6 address takes brackets to be given a port, `[fe80::1]:1883`.
+#[cfg(feature = "mqtt")]
+fn parse_broker(value: &str) -> Result<(String, u16), String> {
+    let unbracketed = |host: &str| {
+        host.strip_prefix('[')
+            .and_then(|host| host.strip_suffix(']'))
+            .unwrap_or(host)
+            .to_string()
+    };
+
+    if unbracketed(value).parse::<IpAddr>().is_ok() {
+        return Ok((unbracketed(value), rpi_controller::mqtt::DEFAULT_PORT));
+    }
+
+    match value.rsplit_once(':') {
+        Some((host, port)) => port
+            .parse()
+            .map(|port| (unbracketed(host), port))
+            .map_err(|_| String::from("expected a port from 0 to 65535")),
+        None => Ok((value.to_string(), rpi_controller::mqtt::DEFAULT_PORT)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn parse_baud_rate(value: &str) -> Result<BaudRate, String> {
+    let rates: Vec<String> = BAUD_RATES
+        .iter()
+        .map(|(rate, _)| rate.to_string())
+        .collect();
+
+    value
+        .parse::<u32>()
+        .ok()
+        .and_then(|value| BAUD_RATES.iter().find(|(rate, _)| *rate == value))
+        .map(|(_, baud_rate)| *baud_rate)
+        .ok_or_else(|| format!("expected one of {}", rates.join(", ")))
+}
+
+fn parse_spi_bus(value: &str) -> Result<spi::Bus, String> {
+    const BUSES: [spi::Bus; 7] = [
+        spi::Bus::Spi0,
+        spi::Bus::Spi1,
+        spi::Bus::Spi2,
+        spi::Bus::Spi3,
+        spi::Bus::Spi4,
+        spi::Bus::Spi5,
+        spi::Bus::Spi6,
+    ];
+
+    value
+        .parse::<usize>()
+        .ok()
+        .and_then(|bus| BUSES.get(bus).copied())
+        .ok_or_else(|| String::from("expected a bus from 0 to 6"))
+}
+
+fn parse_slave_select(value: &str) -> Result<spi::SlaveSelect, String> {
+    const SLAVE_SELECTS: [spi::SlaveSelect; 3] = [
+        spi::SlaveSelect::Ss0,
+        spi::SlaveSelect::Ss1,
+        spi::SlaveSelect::Ss2,
+    ];
+
+    value
+        .parse::<usize>()
+        .ok()
+        .and_then(|slave_select| SLAVE_SELECTS.get(slave_select).copied())
+        .ok_or_else(|| String::from("expected a slave select line from 0 to 2"))
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Sends the frames of a recording again, with the original timing.
+    Replay {
+        /// Recording made with `--record`.
+        file: PathBuf,
+    },
+    /// Listens on every channel for other transmitters, to find a quiet one.
+    Scan {
+        /// Number of times each channel is sampled.
+        #[arg(long, default_value_t = 100)]
+        sweeps: u32,
+
+        /// Also writes the results to this CSV file.
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+    /// Transmits a constant carrier, to check the antenna and shielding with a spectrum analyzer.
+    Carrier {
+        #[arg(long, default_value_t = 125)]
+        channel: u8,
+
+        /// -18dBm, -12dBm, -6dBm or 0dBm.
+        #[arg(long, default_value = "0dBm", allow_hyphen_values = true)]
+        power: PowerLevel,
+
+        /// How long the carrier stays on.
+        #[arg(long, default_value_t = 10)]
+        seconds: u64,
+
+        /// Steps the carrier through every channel instead, checking the module accepts the
+        /// forced PLL lock bit on each one. The actual lock can only be seen on an analyzer.
+        #[arg(long)]
+        check_pll: bool,
+    },
+    /// Prints every register of the radio once configured, and exits.
+    Registers {
+        /// Prints them as JSON, to diff the registers of two radios.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Listens on a channel without acknowledging anything, and prints the decoded payloads.
+    ///
+    /// With `--telemetry`, it expects the dynamic payloads of a link with telemetry.
+    Sniff {
+        #[arg(long, default_value_t = 125)]
+        channel: u8,
+
+        #[arg(long, default_value = "aaaaa")]
+        address: String,
+
+        /// Also writes the payloads to this pcap file, for Wireshark.
+        #[arg(long)]
+        pcap: Option<PathBuf>,
+
+        /// Stops after this long, instead of running until interrupted.
+        #[arg(long)]
+        seconds: Option<u64>,
+    },
+    /// Prints the Wireshark Lua dissector for the payloads, and exits.
+    Dissector,
+    /// Forwards the frames of controllers using `--gateway` to the robot with this radio.
+    Gateway {
+        /// Address and port the frames are received on.
+        #[arg(long, default_value = "0.0.0.0:7870")]
+        listen: String,
+    },
+}
+
+#[derive(Debug, Copy, Clone, ValueEnum)]
+pub enum InputBackend {
+    /// SDL2 game controller API.
+    #[cfg(feature = "sdl2")]
+    Sdl,
+    /// Linux evdev devices under /dev/input, for headless hosts.
+    #[cfg(target_os = "linux")]
+    Evdev,
+    /// Replays the events of a script file instead of reading a gamepad.
+    Script,
+    /// Serves a gamepad page to browsers and phones, on `--web-address`.
+    Web,
+}
+
+impl InputBackend {
+    /// SDL when it's built in, evdev otherwise on Linux, and the web page elsewhere.
+    #[cfg(feature = "sdl2")]
+    const DEFAULT: InputBackend = InputBackend::Sdl;
+    #[cfg(all(not(feature = "sdl2"), target_os = "linux"))]
+    const DEFAULT: InputBackend = InputBackend::Evdev;
+    #[cfg(all(not(feature = "sdl2"), not(target_os = "linux")))]
+    const DEFAULT: InputBackend = InputBackend::Web;
+}
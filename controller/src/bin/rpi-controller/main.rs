@@ -0,0 +1,695 @@
+mod cli;
+
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser};
+use cli::{Cli, Command, InputBackend};
+use rpi_controller::channel::{self, ChannelAction, ChannelManager};
+use rpi_controller::controller::Input;
+use rpi_controller::dashboard::{self, Dashboard, RadioSettings, Stats};
+use rpi_controller::error::AppError;
+#[cfg(target_os = "linux")]
+use rpi_controller::evdev_input;
+use rpi_controller::haptics::Haptics;
+use rpi_controller::health::{HealthEvent, HealthMonitor};
+use rpi_controller::idle::IdlePolicy;
+use rpi_controller::link::{LinkEvent, LinkMonitor};
+use rpi_controller::listener::{Listener, Packet};
+#[cfg(feature = "mqtt")]
+use rpi_controller::mqtt;
+use rpi_controller::pcap::PcapWriter;
+use rpi_controller::recording::{Player, RecordKind, Recorder};
+use rpi_controller::rf24::{DataRate, Delivery, PowerLevel, Radio, SpiConfig};
+#[cfg(feature = "sdl2")]
+use rpi_controller::sdl_input;
+#[cfg(target_os = "linux")]
+use rpi_controller::serial::SerialTransport;
+use rpi_controller::transport::{self, Gateway, Transport, UdpTransport};
+use rpi_controller::{protocol, scan, script, web};
+use std::fs::File;
+use std::io;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Sweeps of the channel scan made to pick a channel at startup.
+const AUTO_CHANNEL_SWEEPS: u32 = 20;
+
+/// Time between two reads of the gamepad while idle and not sending.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+pub fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.remote() && !matches!(cli.command, None | Some(Command::Replay { .. })) {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "only driving and replaying work through a gateway or a dongle, the other commands need the radio",
+            )
+            .exit();
+    }
+
+    match start(&cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn start(cli: &Cli) -> Result<(), AppError> {
+    if let Some(Command::Dissector) = &cli.command {
+        print!("{}", protocol::lua_dissector());
+        return Ok(());
+    }
+
+    let remote: Option<Box<dyn Transport>> = if let Some(gateway) = &cli.gateway {
+        println!("Sending to the gateway at {}", gateway);
+        Some(Box::new(connect(gateway)?))
+    } else {
+        open_dongle(cli)?
+    };
+    if let Some(mut transport) = remote {
+        return match &cli.command {
+            Some(Command::Replay { file }) => replay(transport.as_mut(), file),
+            _ => run(cli, transport.as_mut()),
+        };
+    }
+
+    let mut radio = build_radio(cli, cli.spi_config())?;
+
+    println!("Starting up");
+
+    radio.configure()?;
+
+    let snapshot = radio.register_snapshot()?;
+    if let Some(Command::Registers { json: true }) = &cli.command {
+        println!("{}", serde_json::to_string_pretty(&snapshot).unwrap());
+        return Ok(());
+    }
+    println!("{}", snapshot);
+
+    match &cli.command {
+        None => run(cli, &mut radio),
+        Some(Command::Gateway { listen }) => forward(&mut radio, listen),
+        Some(Command::Replay { file }) => replay(&mut radio, file),
+        Some(Command::Scan { sweeps, csv }) => scan_channels(&mut radio, *sweeps, csv.as_ref()),
+        Some(Command::Carrier {
+            channel,
+            power,
+            seconds,
+            check_pll: false,
+        }) => carrier_wave(&mut radio, *channel, *power, Duration::from_secs(*seconds)),
+        Some(Command::Carrier {
+            power,
+            check_pll: true,
+            ..
+        }) => check_pll_lock(&mut radio, *power),
+        Some(Command::Sniff {
+            channel,
+            address,
+            pcap,
+            seconds,
+        }) => sniff(
+            &mut radio,
+            *channel,
+            address,
+            pcap.as_ref(),
+            seconds.map(Duration::from_secs),
+        ),
+        Some(Command::Registers { .. } | Command::Dissector) => Ok(()),
+    }
+}
+
+/// A radio with the settings of the robot, wired as given.
+fn build_radio(cli: &Cli, spi: SpiConfig) -> Result<Radio, AppError> {
+    let mut radio = Radio::builder("aaaaa")
+        .data_rate(DataRate::_250Kbps)
+        .power_level(PowerLevel::_0dBm)
+        .channel(125)
+        .spi(spi)
+        .build()?;
+    radio.set_ack_payloads(cli.telemetry)?;
+
+    Ok(radio)
+}
+
+/// The transport to the gateway at `address`, on the default port if it has none.
+///
+/// An IPv6 address takes brackets to be given a port, `[fe80::1]:7870`.
+fn connect(address: &str) -> Result<UdpTransport, AppError> {
+    let bare_ip = address
+        .strip_prefix('[')
+        .and_then(|address| address.strip_suffix(']'))
+        .unwrap_or(address)
+        .parse::<IpAddr>();
+
+    let transport = if let Ok(ip) = bare_ip {
+        UdpTransport::connect((ip, transport::DEFAULT_PORT))
+    } else if address.contains(':') {
+        UdpTransport::connect(address)
+    } else {
+        UdpTransport::connect((address, transport::DEFAULT_PORT))
+    };
+    transport.map_err(AppError::Network)
+}
+
+/// The transport through the dongle on `--serial`, if there is one.
+#[cfg(target_os = "linux")]
+fn open_dongle(cli: &Cli) -> Result<Option<Box<dyn Transport>>, AppError> {
+    let Some(port) = &cli.serial else {
+        return Ok(None);
+    };
+    println!("Sending through the dongle on {}", port.display());
+    Ok(Some(Box::new(SerialTransport::open(port, cli.baud)?)))
+}
+
+/// Dongles are only supported on Linux.
+#[cfg(not(target_os = "linux"))]
+fn open_dongle(_cli: &Cli) -> Result<Option<Box<dyn Transport>>, AppError> {
+    Ok(None)
+}
+
+/// Carries on after a retryable error, printing it with `context`, and gives up on the others.
+fn recover(context: &str, error: AppError) -> Result<(), AppError> {
+    if error.is_retryable() {
+        println!("{}: {}", context, error);
+        Ok(())
+    } else {
+        Err(error)
+    }
+}
+
+fn run(cli: &Cli, transport: &mut dyn Transport) -> Result<(), AppError> {
+    let mut status_board = None;
+    let mut controller: Box<dyn Input> = match cli.input {
+        #[cfg(feature = "sdl2")]
+        InputBackend::Sdl => {
+            let mut controller = sdl_input::init()?;
+            if cli.tilt {
+                controller.enable_tilt_steering();
+            }
+            Box::new(controller)
+        }
+        #[cfg(target_os = "linux")]
+        InputBackend::Evdev => Box::new(evdev_input::init()?),
+        // clap requires --script with the script input
+        InputBackend::Script => Box::new(script::init(cli.script.as_ref().unwrap())?),
+        InputBackend::Web => {
+            let input = web::init(cli.web_address.as_str())?;
+            status_board = Some(input.status_board());
+            Box::new(input)
+        }
+    };
+    let mut recorder = cli
+        .record
+        .as_ref()
+        .map(|path| Recorder::create(path).map_err(AppError::file(path)))
+        .transpose()?;
+    let mut link = LinkMonitor::new();
+    let mut haptics = Haptics::new();
+    let mut health = HealthMonitor::new(Instant::now());
+    // clap keeps the dashboard, the channel changes and the listener away from a gateway or a
+    // dongle
+    let dashboard = transport
+        .radio()
+        .filter(|_| cli.tui)
+        .map(|_| Dashboard::start())
+        .transpose()
+        .map_err(AppError::Dashboard)?;
+    let mut stats = Stats::new();
+    let mut last_fifo_read = Instant::now();
+    let mut channels = match transport.radio() {
+        Some(radio) => channel_manager(cli, radio)?,
+        None => None,
+    };
+    let mut idle = cli.power_save.then(IdlePolicy::new);
+    #[cfg(feature = "mqtt")]
+    let mut bridge = cli
+        .mqtt
+        .as_ref()
+        .map(|(host, port)| mqtt::Bridge::connect(host, *port, &cli.mqtt_prefix));
+
+    // Both radios are timed against the same clock
+    let clock = Instant::now();
+    let listener = if cli.listener {
+        let listening_radio = build_radio(cli, cli.listener_spi_config())?;
+        stats.listening = true;
+        Some(Listener::start(listening_radio, clock)?)
+    } else {
+        None
+    };
+
+    if let (Some(channels), Some(radio)) = (&mut channels, transport.radio()) {
+        if let Some(action) = channels.start() {
+            change_channel(channels, action, radio, listener.as_ref())?;
+        }
+    }
+
+    'running: loop {
+        match controller.update_state() {
+            Ok(_) => {}
+            Err(e) => {
+                println!("Shutting down: {}", e);
+                break 'running;
+            }
+        }
+
+        if let Some(radio) = transport.radio() {
+            if let Some(recoveries) = check_health(&mut health, radio) {
+                stats.recoveries = recoveries;
+            }
+        }
+
+        // Only the MQTT bridge changes it, to apply the remote limits
+        #[cfg_attr(not(feature = "mqtt"), allow(unused_mut))]
+        let mut state = *controller.get_state();
+
+        #[cfg(feature = "mqtt")]
+        if let Some(bridge) = &mut bridge {
+            for event in bridge.update() {
+                match event {
+                    mqtt::BridgeEvent::Connected => println!("Connected to the MQTT broker"),
+                    mqtt::BridgeEvent::Unreachable(e) => {
+                        println!("MQTT broker unreachable: {}", e)
+                    }
+                    mqtt::BridgeEvent::InvalidCommand(e) => {
+                        println!("Invalid remote command: {}", e)
+                    }
+                    mqtt::BridgeEvent::Command(command) => {
+                        println!("Remote command: {}", command);
+                        if let mqtt::RemoteCommand::Channel(channel) = command {
+                            match transport.radio() {
+                                Some(radio) => {
+                                    let channels = channels.get_or_insert_with(|| {
+                                        ChannelManager::new(
+                                            radio.channel(),
+                                            Vec::new(),
+                                            radio.ack_payloads(),
+                                            Instant::now(),
+                                        )
+                                    });
+                                    let action = channels.prefer(channel);
+                                    change_channel(channels, action, radio, listener.as_ref())?;
+                                }
+                                None => {
+                                    println!("The channel can only be changed on a local radio")
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            bridge.limit(&mut state);
+        }
+
+        if let Some(idle) = &mut idle {
+            let decision = idle.update(&state, Instant::now());
+            if !decision.send {
+                if let Some(radio) = transport.radio() {
+                    if radio.power_state() != decision.power_state {
+                        if let Err(e) = radio.set_power_state(decision.power_state) {
+                            recover("Failed to change the power state", e.into())?;
+                        }
+                    }
+                }
+                if dashboard.as_ref().is_some_and(Dashboard::quit_requested) {
+                    break 'running;
+                }
+                sleep(IDLE_POLL_INTERVAL);
+                continue 'running;
+            }
+        }
+
+        let frame = state.to_bytes();
+        let delivery = match transport.send(&frame) {
+            Ok(delivery) => {
+                health.send_succeeded();
+                delivery
+            }
+            Err(e) => {
+                if let AppError::Radio(error) = &e {
+                    health.send_failed(error);
+                }
+                recover("Failed to send", e)?;
+                continue 'running;
+            }
+        };
+        stats.record_delivery(&delivery);
+
+        // Driving matters more than the recording, which stops at the first error.
+        if let Some(rec) = &mut recorder {
+            if let Err(e) = record(rec, &frame, &delivery) {
+                println!("Recording stopped: {}", e);
+                recorder = None;
+            }
+        }
+
+        let events = link.update(&delivery, Instant::now());
+        for event in &events {
+            match event {
+                LinkEvent::Up => println!("Link with the robot is up"),
+                LinkEvent::Down => println!("Link with the robot is down"),
+                LinkEvent::Telemetry(telemetry) => stats.telemetry = Some(*telemetry),
+                _ => {}
+            }
+            haptics.handle(controller.as_mut(), event);
+        }
+
+        if let (Some(channels), Some(radio)) = (&mut channels, transport.radio()) {
+            let now = Instant::now();
+            if let Some(action) = channels.update(&events, link.is_up(), now) {
+                change_channel(channels, action, radio, listener.as_ref())?;
+            }
+        }
+
+        if let Some(listener) = &listener {
+            for heard in listener.heard() {
+                match heard {
+                    Ok(heard) => match heard.packet {
+                        Packet::Frame(_) => stats.heard_frames += 1,
+                        Packet::Telemetry(telemetry) => {
+                            stats.heard_telemetry += 1;
+                            stats.telemetry = Some(telemetry);
+                        }
+                        Packet::Other(payload) => {
+                            println!("Heard an unknown payload at {:?}: {:?}", heard.at, payload)
+                        }
+                    },
+                    Err(e) => recover("The listening radio failed", e.into())?,
+                }
+            }
+        }
+
+        stats.state = state;
+        stats.link_up = link.is_up();
+        stats.loss = link.loss();
+        if let Some(radio) = transport.radio() {
+            stats.radio = Some(RadioSettings::of(radio));
+        }
+
+        #[cfg(feature = "mqtt")]
+        if let Some(bridge) = &mut bridge {
+            bridge.publish(&stats);
+        }
+
+        if let Some(status_board) = &status_board {
+            status_board.publish(&stats);
+        }
+
+        if let (Some(dashboard), Some(radio)) = (&dashboard, transport.radio()) {
+            if dashboard.quit_requested() {
+                break 'running;
+            }
+
+            // FIFO_STATUS costs an SPI transfer, it doesn't need to be fresher than the screen.
+            if last_fifo_read.elapsed() >= dashboard::REFRESH_INTERVAL {
+                match radio.fifo_status() {
+                    Ok(fifo_status) => stats.fifo_status = fifo_status,
+                    Err(e) => recover("Failed to read the FIFO status", e.into())?,
+                }
+                last_fifo_read = Instant::now();
+            }
+            stats.status = radio.status();
+            dashboard.publish(&stats);
+        }
+    }
+
+    if let Some(dashboard) = dashboard {
+        // Only printed now that the terminal is back
+        if let Err(e) = dashboard.stop() {
+            println!("Dashboard stopped: {}", e);
+        }
+    }
+
+    if let (Some(recorder), Some(path)) = (&mut recorder, &cli.record) {
+        recorder.flush().map_err(AppError::file(path))?;
+    }
+
+    Ok(())
+}
+
+/// Carries out a channel change, and has the listener follow.
+fn change_channel(
+    channels: &mut ChannelManager,
+    action: ChannelAction,
+    radio: &mut Radio,
+    listener: Option<&Listener>,
+) -> Result<(), AppError> {
+    match channels.apply(action, radio, Instant::now()) {
+        Ok(channel) => {
+            println!("Moved to channel {}", channel);
+            if let Some(listener) = listener {
+                listener.set_channel(channel);
+            }
+            Ok(())
+        }
+        Err(e) => recover("Failed to change channel", e.into()),
+    }
+}
+
+/// Initializes the radio again when it needs to, returning the number of recoveries so far.
+fn check_health(health: &mut HealthMonitor, radio: &mut Radio) -> Option<u32> {
+    match health.check(radio, Instant::now())? {
+        HealthEvent::Recovered { fault, recoveries } => {
+            println!(
+                "Radio initialized again after {:?} ({} so far)",
+                fault, recoveries
+            );
+            Some(recoveries)
+        }
+        HealthEvent::RecoveryFailed { fault, error } => {
+            println!("Radio could not recover from {:?}: {}", fault, error);
+            None
+        }
+    }
+}
+
+/// Records a frame that was sent, and the telemetry that came back with its ACK.
+fn record<W: io::Write>(
+    recorder: &mut Recorder<W>,
+    frame: &[u8],
+    delivery: &Delivery,
+) -> io::Result<()> {
+    recorder.record(RecordKind::ControllerState, frame)?;
+
+    if let Delivery::Acked {
+        payload: Some(payload),
+        ..
+    } = delivery
+    {
+        recorder.record(RecordKind::Telemetry, payload)?;
+    }
+
+    Ok(())
+}
+
+/// Sets up the channel changes asked for on the command line, if any.
+///
+/// The channel the radio starts on is the home channel, the one the robot starts on too.
+fn channel_manager(cli: &Cli, radio: &mut Radio) -> Result<Option<ChannelManager>, AppError> {
+    let home = radio.channel();
+
+    let sequence = if let Some(seed) = cli.hop_seed {
+        channel::hopping_sequence(seed)
+    } else if cli.auto_channel {
+        println!("Looking for a quiet channel");
+        let scan = scan::scan(radio, AUTO_CHANNEL_SWEEPS)?;
+        radio.set_channel(home)?;
+        scan.ranking()
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(ChannelManager::new(
+        home,
+        sequence,
+        radio.ack_payloads(),
+        Instant::now(),
+    )))
+}
+
+/// Sends the controller state frames of a recording, keeping the time between them.
+fn replay(transport: &mut dyn Transport, path: &Path) -> Result<(), AppError> {
+    let player = Player::open(path).map_err(AppError::file(path))?;
+    let started = Instant::now();
+
+    println!("Replaying {}", path.display());
+
+    for record in player {
+        let record = record.map_err(AppError::file(path))?;
+        if record.kind != RecordKind::ControllerState {
+            continue;
+        }
+
+        if let Some(wait) = record.at.checked_sub(started.elapsed()) {
+            sleep(wait);
+        }
+        if let Err(e) = transport.send(&record.payload) {
+            recover("Failed to send", e)?;
+        }
+    }
+
+    println!("Replay finished");
+    Ok(())
+}
+
+/// Sends the frames received from controllers over UDP with the radio, for `--gateway`.
+///
+/// The robot stops on its link timeout when the frames stop coming, as with a local radio.
+fn forward(radio: &mut Radio, address: &str) -> Result<(), AppError> {
+    let gateway = Gateway::bind(address).map_err(AppError::Network)?;
+    let mut health = HealthMonitor::new(Instant::now());
+
+    println!(
+        "Forwarding the frames received on {}",
+        gateway.local_addr().map_err(AppError::Network)?
+    );
+
+    loop {
+        check_health(&mut health, radio);
+
+        match gateway.forward(radio) {
+            Ok(()) => health.send_succeeded(),
+            Err(e) => {
+                if let AppError::Radio(error) = &e {
+                    health.send_failed(error);
+                }
+                recover("Failed to forward", e)?;
+            }
+        }
+    }
+}
+
+/// Prints how busy each channel is, and the quietest one.
+fn scan_channels(radio: &mut Radio, sweeps: u32, csv: Option<&PathBuf>) -> Result<(), AppError> {
+    println!("Scanning {} channels {} times", scan::CHANNELS, sweeps);
+
+    let scan = scan::scan(radio, sweeps)?;
+
+    print!("{}", scan.histogram());
+    println!("Quietest channel: {}", scan.quietest_channel());
+
+    if let Some(path) = csv {
+        let file = File::create(path).map_err(AppError::file(path))?;
+        scan.write_csv(file).map_err(AppError::file(path))?;
+    }
+
+    Ok(())
+}
+
+/// Prints the payloads heard on `channel` and `address`, without acknowledging them.
+///
+/// The pcap packets hold the channel in their first byte, followed by the payload.
+fn sniff(
+    radio: &mut Radio,
+    channel: u8,
+    address: &str,
+    pcap: Option<&PathBuf>,
+    duration: Option<Duration>,
+) -> Result<(), AppError> {
+    let mut pcap = pcap
+        .map(|path| {
+            File::create(path)
+                .and_then(|file| PcapWriter::new(io::BufWriter::new(file)))
+                .map(|writer| (path, writer))
+                .map_err(AppError::file(path))
+        })
+        .transpose()?;
+
+    radio.set_passive(true);
+    radio.set_address(address)?;
+    radio.set_channel(channel)?;
+    radio.configure()?;
+    radio.start_listening()?;
+
+    println!("Sniffing channel {} on address {}", channel, address);
+
+    let clock = Instant::now();
+    while duration.is_none_or(|duration| clock.elapsed() < duration) {
+        let payload = match radio.receive() {
+            Ok(Some(payload)) => payload,
+            Ok(None) => {
+                sleep(Duration::from_micros(200));
+                continue;
+            }
+            Err(e) => {
+                recover("Failed to receive", e.into())?;
+                continue;
+            }
+        };
+
+        let at = clock.elapsed();
+        println!(
+            "{:>4}.{:06} ch{} {}",
+            at.as_secs(),
+            at.subsec_micros(),
+            channel,
+            protocol::describe(&payload)
+        );
+
+        if let Some((path, writer)) = &mut pcap {
+            let packet = [&[channel], payload.as_slice()].concat();
+            writer
+                .write_packet(SystemTime::now(), &packet)
+                .map_err(AppError::file(*path))?;
+        }
+    }
+
+    radio.stop_listening()?;
+    Ok(())
+}
+
+/// Keeps a constant carrier on for `duration`, for bench testing the RF side.
+fn carrier_wave(
+    radio: &mut Radio,
+    channel: u8,
+    power_level: PowerLevel,
+    duration: Duration,
+) -> Result<(), AppError> {
+    println!(
+        "Carrier on channel {} ({}MHz) at {} for {}s",
+        channel,
+        2400 + channel as u32,
+        power_level,
+        duration.as_secs()
+    );
+
+    radio.start_carrier_wave(channel, power_level)?;
+    if !radio.forced_pll_lock_set(channel)? {
+        println!("The module didn't take the carrier wave settings");
+    }
+    sleep(duration);
+    radio.stop_carrier_wave()?;
+
+    println!("Carrier off");
+    Ok(())
+}
+
+/// Steps the carrier through every channel, reporting the ones where the module didn't accept
+/// the forced PLL lock bit.
+fn check_pll_lock(radio: &mut Radio, power_level: PowerLevel) -> Result<(), AppError> {
+    let mut failed = Vec::new();
+
+    for channel in 0..scan::CHANNELS as u8 {
+        radio.start_carrier_wave(channel, power_level)?;
+        sleep(Duration::from_millis(1));
+        if !radio.forced_pll_lock_set(channel)? {
+            failed.push(channel);
+        }
+    }
+    radio.stop_carrier_wave()?;
+
+    if failed.is_empty() {
+        println!(
+            "Forced PLL lock bit accepted on all {} channels",
+            scan::CHANNELS
+        );
+    } else {
+        println!("Forced PLL lock bit not accepted on channels {:?}", failed);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,310 @@
+use crate::link::LinkEvent;
+use crate::rf24::{Delivery, RF24Error, Radio};
+use crate::scan::CHANNELS;
+use std::time::{Duration, Instant};
+
+/// Value of the L2 field that marks a control frame. Controller states never have it, their
+/// triggers are capped at `MAX_TRIGGER` when encoded.
+pub const CONTROL_MARKER: [u8; 2] = [0xFF, 0xFF];
+
+/// Control message that moves the robot to the channel in the next byte.
+pub const SWITCH_CHANNEL: u8 = 0x01;
+
+/// Times the switch is sent when there are no ACKs to tell whether it arrived.
+const UNACKNOWLEDGED_SWITCH_REPEATS: usize = 5;
+
+/// Tries to get an ACK for the switch before moving anyway.
+const ACKNOWLEDGED_SWITCH_ATTEMPTS: usize = 10;
+
+/// Time to wait after a switch before moving again on loss.
+const SWITCH_COOLDOWN: Duration = Duration::from_secs(5);
+
+/// Time the link has to come up on a new channel before going back to the home channel.
+/// The robot goes back after `RADIO_HOME_TIMEOUT` without payloads.
+const HOME_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A frame asking the robot to move to `channel`.
+///
+/// It has the size of a controller state frame, with 0xFFFF in the L2 field, which a trigger
+/// never reaches (see `MAX_TRIGGER`), so the robot can tell them apart.
+pub fn switch_frame(channel: u8) -> [u8; 13] {
+    let mut frame = [0u8; 13];
+    frame[1..3].copy_from_slice(&CONTROL_MARKER);
+    frame[3] = SWITCH_CHANNEL;
+    frame[4] = channel;
+    frame
+}
+
+/// A reproducible hopping sequence over every channel, from a seed both ends can share.
+pub fn hopping_sequence(seed: u64) -> Vec<u8> {
+    let mut channels: Vec<u8> = (0..CHANNELS as u8).collect();
+
+    // xorshift64, which gets stuck on 0
+    let mut state = (seed ^ 0x9E37_79B9_7F4A_7C15).max(1);
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    for i in (1..channels.len()).rev() {
+        let j = (next() % (i as u64 + 1)) as usize;
+        channels.swap(i, j);
+    }
+
+    channels
+}
+
+/// What the control loop has to do with the radio channel.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ChannelAction {
+    /// Tell the robot to move to the channel, then move too.
+    Announce(u8),
+    /// Go back to the home channel without telling the robot, which can't be reached.
+    ReturnHome,
+}
+
+/// Picks the channel the controller and the robot talk on.
+///
+/// Both ends start on the home channel, the one the robot firmware is built with. The
+/// controller then announces the first channel of the sequence, and moves to the next one when
+/// the loss rises. If the link goes down, or never comes up on the new channel, both ends go
+/// back to the home channel, where the controller announces the channel again.
+///
+/// Without ACK payloads there are no link events, and no way to tell that the robot is lost, so
+/// the channel only changes with the first announcement and when asked from outside.
+pub struct ChannelManager {
+    home: u8,
+    ack_payloads: bool,
+    current: u8,
+    sequence: Vec<u8>,
+    position: usize,
+    switched_at: Instant,
+}
+
+impl ChannelManager {
+    /// `ack_payloads` tells whether the radio gets the ACK payloads the link events come from.
+    pub fn new(home: u8, sequence: Vec<u8>, ack_payloads: bool, now: Instant) -> Self {
+        ChannelManager {
+            home,
+            ack_payloads,
+            current: home,
+            sequence,
+            position: 0,
+            switched_at: now,
+        }
+    }
+
+    fn preferred(&self) -> u8 {
+        self.sequence
+            .get(self.position)
+            .copied()
+            .unwrap_or(self.home)
+    }
+
+    /// The first announcement, right after startup.
+    pub fn start(&self) -> Option<ChannelAction> {
+        let preferred = self.preferred();
+        (preferred != self.home).then_some(ChannelAction::Announce(preferred))
+    }
+
+    /// Decides what to do after a payload was sent, from the link events it caused.
+    ///
+    /// Without ACK payloads the link never looks up, so it never decides anything: going home
+    /// would leave the robot behind on the new channel.
+    pub fn update(
+        &mut self,
+        events: &[LinkEvent],
+        link_up: bool,
+        now: Instant,
+    ) -> Option<ChannelAction> {
+        if !self.ack_payloads {
+            return None;
+        }
+
+        let since_switch = now.duration_since(self.switched_at);
+
+        if self.current != self.home {
+            if events.contains(&LinkEvent::Down) || (!link_up && since_switch >= HOME_TIMEOUT) {
+                return Some(ChannelAction::ReturnHome);
+            }
+
+            let loss_spike = events
+                .iter()
+                .any(|event| matches!(event, LinkEvent::LossSpike(_)));
+            if loss_spike && since_switch >= SWITCH_COOLDOWN {
+                self.position = (self.position + 1) % self.sequence.len();
+                if self.preferred() == self.current {
+                    self.position = (self.position + 1) % self.sequence.len();
+                }
+                return Some(ChannelAction::Announce(self.preferred()));
+            }
+        } else if events.contains(&LinkEvent::Up) && self.preferred() != self.home {
+            return Some(ChannelAction::Announce(self.preferred()));
+        }
+
+        None
+    }
+
+    /// Moves to `channel` when asked from outside, putting it first in the sequence.
+    pub fn prefer(&mut self, channel: u8) -> ChannelAction {
+        self.sequence.retain(|&other| other != channel);
+        self.sequence.insert(0, channel);
+        self.position = 0;
+        ChannelAction::Announce(channel)
+    }
+
+    /// Carries out the action on the radio.
+    pub fn apply(
+        &mut self,
+        action: ChannelAction,
+        radio: &mut Radio,
+        now: Instant,
+    ) -> Result<u8, RF24Error> {
+        let channel = match action {
+            ChannelAction::Announce(channel) => {
+                announce(radio, channel)?;
+                channel
+            }
+            ChannelAction::ReturnHome => self.home,
+        };
+
+        radio.set_channel(channel)?;
+        self.current = channel;
+        self.switched_at = now;
+
+        Ok(channel)
+    }
+}
+
+/// Tells the robot to move to `channel`, on the current one.
+fn announce(radio: &mut Radio, channel: u8) -> Result<(), RF24Error> {
+    let frame = switch_frame(channel);
+
+    for _ in 0..ACKNOWLEDGED_SWITCH_ATTEMPTS {
+        match radio.send(&frame)? {
+            Delivery::Acked { .. } => return Ok(()),
+            Delivery::Lost => {}
+            Delivery::Unacknowledged => {
+                radio.send_copies(&frame, UNACKNOWLEDGED_SWITCH_REPEATS - 1)?;
+                return Ok(());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rf24::mock::{configured_radio, MockBus};
+
+    #[test]
+    fn hopping_sequences_are_reproducible() {
+        let sequence = hopping_sequence(42);
+
+        assert_eq!(sequence, hopping_sequence(42));
+        assert_ne!(sequence, hopping_sequence(43));
+
+        let mut sorted = sequence.clone();
+        sorted.sort();
+        assert_eq!(sorted, (0..CHANNELS as u8).collect::<Vec<u8>>());
+    }
+
+    #[test]
+    fn moves_on_loss_and_returns_home_on_link_down() {
+        let start = Instant::now();
+        let mut manager = ChannelManager::new(125, vec![40, 70, 100], true, start);
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        radio.set_ack_payloads(true).unwrap();
+        radio.configure().unwrap();
+
+        assert_eq!(manager.start(), Some(ChannelAction::Announce(40)));
+        manager
+            .apply(ChannelAction::Announce(40), &mut radio, start)
+            .unwrap();
+        assert_eq!(radio.channel(), 40);
+        assert_eq!(bus.transmitted(), vec![switch_frame(40).to_vec()]);
+
+        let up = start + Duration::from_millis(10);
+        assert_eq!(manager.update(&[LinkEvent::Up], true, up), None);
+
+        // Too soon after the last switch
+        let spike = [LinkEvent::LossSpike(0.4)];
+        assert_eq!(
+            manager.update(&spike, true, start + Duration::from_secs(1)),
+            None
+        );
+
+        let later = start + SWITCH_COOLDOWN;
+        assert_eq!(
+            manager.update(&spike, true, later),
+            Some(ChannelAction::Announce(70))
+        );
+        manager
+            .apply(ChannelAction::Announce(70), &mut radio, later)
+            .unwrap();
+
+        let down = later + Duration::from_secs(1);
+        assert_eq!(
+            manager.update(&[LinkEvent::Down], false, down),
+            Some(ChannelAction::ReturnHome)
+        );
+        manager
+            .apply(ChannelAction::ReturnHome, &mut radio, down)
+            .unwrap();
+        assert_eq!(radio.channel(), 125);
+
+        assert_eq!(
+            manager.update(&[LinkEvent::Up], true, down),
+            Some(ChannelAction::Announce(70))
+        );
+    }
+
+    #[test]
+    fn returns_home_when_the_link_never_comes_up_with_acks() {
+        let start = Instant::now();
+        let bus = MockBus::new();
+        bus.chip.lock().unwrap().in_range = false;
+        let mut radio = configured_radio(&bus);
+        radio.set_ack_payloads(true).unwrap();
+        radio.configure().unwrap();
+        let mut manager = ChannelManager::new(125, vec![40], radio.ack_payloads(), start);
+        manager
+            .apply(ChannelAction::Announce(40), &mut radio, start)
+            .unwrap();
+
+        assert_eq!(
+            manager.update(&[], false, start + Duration::from_secs(1)),
+            None
+        );
+        assert_eq!(
+            manager.update(&[], false, start + HOME_TIMEOUT),
+            Some(ChannelAction::ReturnHome)
+        );
+    }
+
+    #[test]
+    fn stays_on_the_announced_channel_without_acks() {
+        let start = Instant::now();
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        let mut manager = ChannelManager::new(125, vec![40], radio.ack_payloads(), start);
+        manager
+            .apply(ChannelAction::Announce(40), &mut radio, start)
+            .unwrap();
+
+        // Every frame is sent without an ACK, so the link never comes up
+        let later = start + HOME_TIMEOUT * 2;
+        assert_eq!(radio.send(&[0; 13]).unwrap(), Delivery::Unacknowledged);
+        assert_eq!(manager.update(&[], false, later), None);
+        assert_eq!(
+            manager.update(&[LinkEvent::LossSpike(1.0)], false, later),
+            None
+        );
+        assert_eq!(radio.channel(), 40);
+    }
+}
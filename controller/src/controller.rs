@@ -1,27 +1,118 @@
-use sdl2::{
-    controller::{Axis, Button, GameController},
-    event::Event,
-    EventPump, GameControllerSubsystem,
-};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
 
 const JOYSTICK_DEADZONE: i16 = 4000;
 
+/// Highest trigger value sent to the robot. Above it L2 could look like the marker of a
+/// control frame.
+pub const MAX_TRIGGER: u16 = i16::MAX as u16;
+
+/// The gamepad buttons that are sent to the robot, named after the SDL game controller ones.
+///
+/// The face buttons are named by position, `A` being the bottom one (cross on a PlayStation
+/// gamepad).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Button {
+    A,
+    B,
+    X,
+    Y,
+    Back,
+    Start,
+    LeftShoulder,
+    RightShoulder,
+}
+
+/// The gamepad axes, named after the SDL game controller ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Axis {
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    TriggerLeft,
+    TriggerRight,
+}
+
+/// Why an input stopped, or couldn't start.
 #[derive(Debug)]
+pub enum InputError {
+    /// The user quit the application.
+    Quit,
+    /// The script reached its `end` event.
+    ScriptFinished,
+    /// SDL or its game controller subsystem couldn't be initialized.
+    Sdl(String),
+    /// `/dev/input` can't be watched for gamepads.
+    #[cfg(target_os = "linux")]
+    Inotify(nix::Error),
+    /// The script can't be read or has a mistake, the reason is in the message.
+    Script(String),
+    /// The web page for browser gamepads can't be served.
+    Web(std::io::Error),
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Quit => write!(f, "quit"),
+            InputError::ScriptFinished => write!(f, "script finished"),
+            InputError::Sdl(e) => write!(f, "SDL error: {}", e),
+            #[cfg(target_os = "linux")]
+            InputError::Inotify(e) => write!(f, "can't watch for gamepads: {}", e),
+            InputError::Script(e) => write!(f, "invalid script: {}", e),
+            InputError::Web(e) => write!(f, "can't serve the gamepad page: {}", e),
+        }
+    }
+}
+
+impl Error for InputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            #[cfg(target_os = "linux")]
+            InputError::Inotify(e) => Some(e),
+            InputError::Web(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// A source of gamepad input that keeps a `ControllerState` up to date.
+pub trait Input {
+    /// Processes the pending input events and updates the controller state.
+    ///
+    /// Returns an error when the input is over, usually because the user quit the application.
+    fn update_state(&mut self) -> Result<(), InputError>;
+
+    fn get_state(&self) -> &ControllerState;
+
+    /// Rumbles the gamepad, if it has motors. The intensities go from 0 to 0xFFFF.
+    fn rumble(&mut self, _low_frequency: u16, _high_frequency: u16, _duration: Duration) {}
+
+    /// Sets the color of the gamepad light bar, if it has one.
+    fn set_led(&mut self, _red: u8, _green: u8, _blue: u8) {}
+}
+
+/// Missing fields are left neutral when deserialized, which is how browsers send it.
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct ControllerState {
-    x: bool,
-    circle: bool,
-    square: bool,
-    triangle: bool,
-    select: bool,
-    start: bool,
-    l1: bool,
-    r1: bool,
-    l2: u16,
-    r2: u16,
-    yaw: i16,
-    throttle: i16,
-    pitch: i16,
-    roll: i16,
+    pub x: bool,
+    pub circle: bool,
+    pub square: bool,
+    pub triangle: bool,
+    pub select: bool,
+    pub start: bool,
+    pub l1: bool,
+    pub r1: bool,
+    pub l2: u16,
+    pub r2: u16,
+    pub yaw: i16,
+    pub throttle: i16,
+    pub pitch: i16,
+    pub roll: i16,
 }
 
 impl ControllerState {
@@ -44,8 +135,28 @@ impl ControllerState {
         }
     }
 
+    /// Whether nothing is pressed and the sticks are centered.
+    pub fn is_neutral(&self) -> bool {
+        let buttons = [
+            self.x,
+            self.circle,
+            self.square,
+            self.triangle,
+            self.select,
+            self.start,
+            self.l1,
+            self.r1,
+        ];
+        let axes = [self.yaw, self.throttle, self.pitch, self.roll];
+
+        !buttons.contains(&true) && self.l2 == 0 && self.r2 == 0 && axes == [0; 4]
+    }
+
+    /// The frame sent to the robot, with the triggers capped at `MAX_TRIGGER`.
     pub fn to_bytes(&self) -> [u8; 13] {
         let mut bytes: [u8; 13] = [0; 13];
+        let l2 = self.l2.min(MAX_TRIGGER);
+        let r2 = self.r2.min(MAX_TRIGGER);
 
         let buttons: u8 = (self.x as u8)
             | ((self.circle as u8) << 1)
@@ -57,10 +168,10 @@ impl ControllerState {
             | ((self.r1 as u8) << 7);
 
         bytes[0] = buttons;
-        bytes[1] = (self.l2 >> 8) as u8;
-        bytes[2] = self.l2 as u8;
-        bytes[3] = (self.r2 >> 8) as u8;
-        bytes[4] = self.r2 as u8;
+        bytes[1] = (l2 >> 8) as u8;
+        bytes[2] = l2 as u8;
+        bytes[3] = (r2 >> 8) as u8;
+        bytes[4] = r2 as u8;
         bytes[5] = (self.yaw >> 8) as u8;
         bytes[6] = self.yaw as u8;
         bytes[7] = (self.throttle >> 8) as u8;
@@ -83,7 +194,6 @@ impl ControllerState {
             Button::Start => self.start = value,
             Button::LeftShoulder => self.l1 = value,
             Button::RightShoulder => self.r1 = value,
-            _ => {}
         }
     }
 
@@ -93,99 +203,28 @@ impl ControllerState {
             Axis::LeftY => self.throttle = self.calculate_axis_value_with_deadzone(value),
             Axis::RightX => self.roll = self.calculate_axis_value_with_deadzone(value),
             Axis::RightY => self.pitch = self.calculate_axis_value_with_deadzone(value),
-            Axis::TriggerLeft => self.l2 = value as u16,
-            Axis::TriggerRight => self.r2 = value as u16,
-        }
-    }
-
-    fn calculate_axis_value_with_deadzone(&self, value: i16) -> i16 {
-        if value < -JOYSTICK_DEADZONE || value > JOYSTICK_DEADZONE {
-            value
-        } else {
-            0
+            // Triggers go from 0, a negative value would wrap around to the control marker
+            Axis::TriggerLeft => self.l2 = value.max(0) as u16,
+            Axis::TriggerRight => self.r2 = value.max(0) as u16,
         }
     }
-}
 
-struct Sdl {
-    sdl_context: sdl2::Sdl,
-    controller_subsystem: GameControllerSubsystem,
-    event_pump: EventPump,
-    controller: Option<GameController>,
-}
-
-pub struct Controller {
-    state: ControllerState,
-    sdl: Sdl,
-}
-
-impl Controller {
-    pub fn get_state(&self) -> &ControllerState {
-        &self.state
+    /// Sets the yaw directly, for steering sources other than the left stick.
+    pub fn set_yaw(&mut self, value: i16) {
+        self.yaw = value;
     }
 
-    /// Listen for the SDL events and updates the controller state when a controller event is received.
-    ///
-    /// Returns an error if the user has quit the application.
-    pub fn update_state(&mut self) -> Result<(), String> {
-        let Sdl {
-            controller_subsystem,
-            event_pump,
-            controller,
-            ..
-        } = &mut self.sdl;
-
-        let controller_state = &mut self.state;
-
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => {
-                    return Err(String::from("Quit"));
-                }
-                Event::ControllerDeviceAdded { which, .. } => {
-                    if controller_subsystem.num_joysticks().unwrap() > 1 {
-                        println!(
-                            "More than one controller was attached. Only one can be used at a time."
-                        );
-                    } else {
-                        let new_controller = controller_subsystem.open(which).unwrap();
-                        println!("Controller attached: {}", new_controller.name());
-                        *controller = Some(new_controller);
-                    }
-                }
-                Event::ControllerDeviceRemoved { which, .. } => {
-                    *controller = None;
-                    println!("Joystick detached: {}", which);
-                }
-                Event::ControllerAxisMotion { axis, value, .. } => {
-                    controller_state.update_axis(axis, value);
-                }
-                Event::ControllerButtonDown { button, .. } => {
-                    controller_state.update_button(button, true);
-                }
-                Event::ControllerButtonUp { button, .. } => {
-                    controller_state.update_button(button, false);
-                }
-                _ => {}
-            }
+    fn calculate_axis_value_with_deadzone(&self, value: i16) -> i16 {
+        if (-JOYSTICK_DEADZONE..=JOYSTICK_DEADZONE).contains(&value) {
+            0
+        } else {
+            value
         }
-
-        Ok(())
     }
 }
 
-pub fn init() -> Controller {
-    let sdl_context = sdl2::init().unwrap();
-    let controller_subsystem = sdl_context.game_controller().unwrap();
-    let event_pump = sdl_context.event_pump().unwrap();
-
-    Controller {
-        state: ControllerState::new(),
-        sdl: Sdl {
-            sdl_context,
-            controller_subsystem,
-            event_pump,
-            controller: None,
-        },
+impl Default for ControllerState {
+    fn default() -> Self {
+        ControllerState::new()
     }
 }
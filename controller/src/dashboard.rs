@@ -0,0 +1,97 @@
+use crate::controller::ControllerState;
+use crate::rf24::{DataRate, Delivery, FifoStatus, PowerLevel, Radio, Status};
+use crate::telemetry::Telemetry;
+use serde::Serialize;
+use std::time::Duration;
+
+#[cfg(unix)]
+mod screen;
+
+#[cfg(unix)]
+pub use screen::Dashboard;
+
+pub const REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Everything the control loop knows about the session, published to the dashboard.
+#[derive(Debug, Copy, Clone)]
+pub struct Stats {
+    pub state: ControllerState,
+    pub frames: u64,
+    pub acked: u64,
+    pub lost: u64,
+    pub retries: u64,
+    pub last_retries: u8,
+    pub recoveries: u32,
+    /// Whether a second radio listens, and hears the counts below.
+    pub listening: bool,
+    pub heard_frames: u64,
+    pub heard_telemetry: u64,
+    /// The settings of the local radio, `None` without one.
+    pub radio: Option<RadioSettings>,
+    pub status: Status,
+    pub fifo_status: FifoStatus,
+    pub link_up: bool,
+    pub loss: f32,
+    pub telemetry: Option<Telemetry>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats {
+            state: ControllerState::new(),
+            frames: 0,
+            acked: 0,
+            lost: 0,
+            retries: 0,
+            last_retries: 0,
+            recoveries: 0,
+            listening: false,
+            heard_frames: 0,
+            heard_telemetry: 0,
+            radio: None,
+            status: Status::default(),
+            fifo_status: FifoStatus::default(),
+            link_up: false,
+            loss: 0.0,
+            telemetry: None,
+        }
+    }
+
+    pub fn record_delivery(&mut self, delivery: &Delivery) {
+        self.frames += 1;
+        match delivery {
+            Delivery::Unacknowledged => {}
+            Delivery::Acked { retries, .. } => {
+                self.acked += 1;
+                self.retries += *retries as u64;
+                self.last_retries = *retries;
+            }
+            Delivery::Lost => self.lost += 1,
+        }
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+/// The radio settings, which the channel changes and the remote commands can change while the
+/// controller runs.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct RadioSettings {
+    pub channel: u8,
+    pub data_rate: DataRate,
+    pub power_level: PowerLevel,
+}
+
+impl RadioSettings {
+    pub fn of(radio: &Radio) -> Self {
+        RadioSettings {
+            channel: radio.channel(),
+            data_rate: radio.data_rate(),
+            power_level: radio.power_level(),
+        }
+    }
+}
@@ -0,0 +1,416 @@
+//! The dashboard in the terminal, on Unix where the standard output can be captured for the log.
+
+use super::{Stats, REFRESH_INTERVAL};
+use crate::controller::ControllerState;
+use nix::unistd::{dup, dup2, pipe};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Gauge, Paragraph};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+const LOG_LINES: usize = 50;
+
+struct Shared {
+    stats: Stats,
+    log: VecDeque<String>,
+    /// What stopped the drawing thread, given back by `Dashboard::stop`.
+    error: Option<io::Error>,
+}
+
+/// A live view of the input and the link, drawn in the terminal by its own thread.
+///
+/// The control loop only copies its `Stats` into the dashboard, and skips it when the drawing
+/// thread is busy with them, so the send path never waits on the terminal. While the dashboard
+/// runs the standard output is captured and shown in the log panel.
+pub struct Dashboard {
+    shared: Arc<Mutex<Shared>>,
+    quit: Arc<AtomicBool>,
+    stdout: OwnedFd,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl Dashboard {
+    pub fn start() -> io::Result<Dashboard> {
+        let shared = Arc::new(Mutex::new(Shared {
+            stats: Stats::new(),
+            log: VecDeque::with_capacity(LOG_LINES),
+            error: None,
+        }));
+        let quit = Arc::new(AtomicBool::new(false));
+
+        // Keep the real standard output for the terminal, and send what gets printed to the log.
+        let stdout = unsafe { OwnedFd::from_raw_fd(dup(io::stdout().as_raw_fd())?) };
+        let (log_reader, log_writer) = pipe()?;
+        dup2(log_writer.as_raw_fd(), io::stdout().as_raw_fd())?;
+
+        let log_thread = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || capture_log(File::from(log_reader), shared))
+        };
+
+        let terminal_output = File::from(stdout.try_clone()?);
+        let draw_thread = {
+            let shared = Arc::clone(&shared);
+            let quit = Arc::clone(&quit);
+            thread::spawn(move || {
+                // Printing it now would draw over the dashboard
+                if let Err(e) = draw_loop(terminal_output, &shared, &quit) {
+                    shared.lock().unwrap().error = Some(e);
+                }
+                quit.store(true, Ordering::Relaxed);
+            })
+        };
+
+        Ok(Dashboard {
+            shared,
+            quit,
+            stdout,
+            threads: vec![log_thread, draw_thread],
+        })
+    }
+
+    /// Hands the latest stats to the drawing thread, unless it's reading them right now.
+    pub fn publish(&self, stats: &Stats) {
+        if let Ok(mut shared) = self.shared.try_lock() {
+            shared.stats = *stats;
+        }
+    }
+
+    /// Whether the user asked to quit from the dashboard.
+    ///
+    /// The dashboard also quits by itself when it fails to draw, see `stop`.
+    pub fn quit_requested(&self) -> bool {
+        self.quit.load(Ordering::Relaxed)
+    }
+
+    /// Gives the terminal and the standard output back.
+    ///
+    /// Returns the error that stopped the dashboard, if it didn't stop because it was asked to.
+    pub fn stop(mut self) -> io::Result<()> {
+        self.shut_down();
+        match self.shared.lock().unwrap().error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn shut_down(&mut self) {
+        self.quit.store(true, Ordering::Relaxed);
+
+        // Closes the last write end of the log pipe, which ends the capture.
+        let _ = dup2(self.stdout.as_raw_fd(), io::stdout().as_raw_fd());
+
+        for thread in self.threads.drain(..) {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+fn capture_log(output: File, shared: Arc<Mutex<Shared>>) {
+    for line in BufReader::new(output).lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        let mut shared = shared.lock().unwrap();
+        if shared.log.len() == LOG_LINES {
+            shared.log.pop_front();
+        }
+        shared.log.push_back(line);
+    }
+}
+
+fn draw_loop(output: File, shared: &Mutex<Shared>, quit: &AtomicBool) -> io::Result<()> {
+    let mut output = output;
+    enable_raw_mode()?;
+    execute!(output, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(output))?;
+
+    let mut last_frames = 0;
+    let mut last_sample = Instant::now();
+    let mut send_rate = 0.0;
+
+    let result = loop {
+        if quit.load(Ordering::Relaxed) {
+            break Ok(());
+        }
+
+        let (stats, log) = {
+            let shared = shared.lock().unwrap();
+            (shared.stats, shared.log.clone())
+        };
+
+        let elapsed = last_sample.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            send_rate = (stats.frames - last_frames) as f64 / elapsed.as_secs_f64();
+            last_frames = stats.frames;
+            last_sample = Instant::now();
+        }
+
+        if let Err(e) = terminal.draw(|frame| draw(frame, &stats, send_rate, &log)) {
+            break Err(e);
+        }
+
+        // Raw mode swallows Ctrl+C, so it has to be handled here.
+        match event::poll(REFRESH_INTERVAL) {
+            Ok(true) => {
+                if let Ok(Event::Key(key)) = event::read() {
+                    let ctrl_c = key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(KeyModifiers::CONTROL);
+                    if key.kind == KeyEventKind::Press && (key.code == KeyCode::Char('q') || ctrl_c)
+                    {
+                        quit.store(true, Ordering::Relaxed);
+                    }
+                }
+            }
+            Ok(false) => {}
+            Err(e) => break Err(e),
+        }
+    };
+
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    disable_raw_mode()?;
+
+    result
+}
+
+fn draw(frame: &mut Frame, stats: &Stats, send_rate: f64, log: &VecDeque<String>) {
+    let [top, robot, log_area] = Layout::vertical([
+        Constraint::Length(10),
+        Constraint::Length(4),
+        Constraint::Min(3),
+    ])
+    .areas(frame.area());
+    let [input, radio] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(top);
+
+    draw_input(frame, input, &stats.state);
+    draw_radio(frame, radio, stats, send_rate);
+    draw_robot(frame, robot, stats);
+
+    let lines: Vec<Line> = log
+        .iter()
+        .rev()
+        .take(log_area.height.saturating_sub(2) as usize)
+        .rev()
+        .map(|line| Line::raw(line.as_str()))
+        .collect();
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Log (q to quit) ")),
+        log_area,
+    );
+}
+
+fn draw_input(frame: &mut Frame, area: Rect, state: &ControllerState) {
+    let block = Block::bordered().title(" Input ");
+    let inner = block.inner(area);
+    frame.render_widget(block, area);
+
+    let rows = Layout::vertical([Constraint::Length(1); 8]).split(inner);
+
+    let sticks = [
+        ("Yaw", state.yaw),
+        ("Throttle", state.throttle),
+        ("Pitch", state.pitch),
+        ("Roll", state.roll),
+    ];
+    for (row, (name, value)) in rows.iter().zip(sticks) {
+        let ratio = (value as f64 + 32_768.0) / 65_535.0;
+        frame.render_widget(axis_gauge(name, ratio, value.to_string()), *row);
+    }
+
+    let triggers = [("L2", state.l2), ("R2", state.r2)];
+    for (row, (name, value)) in rows[4..].iter().zip(triggers) {
+        let ratio = (value as f64 / 32_768.0).min(1.0);
+        frame.render_widget(axis_gauge(name, ratio, value.to_string()), *row);
+    }
+
+    let buttons = [
+        ("X", state.x),
+        ("O", state.circle),
+        ("[]", state.square),
+        ("/\\", state.triangle),
+        ("SELECT", state.select),
+        ("START", state.start),
+        ("L1", state.l1),
+        ("R1", state.r1),
+    ];
+    let spans: Vec<Span> = buttons
+        .iter()
+        .flat_map(|(name, pressed)| {
+            let style = if *pressed {
+                Style::new().black().on_green()
+            } else {
+                Style::new().dark_gray()
+            };
+            [Span::styled(format!(" {} ", name), style), Span::raw(" ")]
+        })
+        .collect();
+    frame.render_widget(Paragraph::new(Line::from(spans)), rows[7]);
+}
+
+fn axis_gauge(name: &str, ratio: f64, label: String) -> Gauge<'static> {
+    Gauge::default()
+        .gauge_style(Style::new().cyan().on_black())
+        .ratio(ratio.clamp(0.0, 1.0))
+        .label(format!("{:<8} {:>6}", name, label))
+}
+
+fn draw_radio(frame: &mut Frame, area: Rect, stats: &Stats, send_rate: f64) {
+    let status = &stats.status;
+    let fifo = &stats.fifo_status;
+
+    let settings = match &stats.radio {
+        Some(radio) => format!(
+            "Channel {}   Rate {}   Power {}",
+            radio.channel, radio.data_rate, radio.power_level
+        ),
+        None => String::from("No local radio"),
+    };
+
+    let mut lines = vec![
+        Line::raw(settings),
+        Line::raw(format!("Sent {} frames, {:.0}/s", stats.frames, send_rate)),
+        Line::raw(format!(
+            "Acked {}   Lost {}   Retries {} (last {})",
+            stats.acked, stats.lost, stats.retries, stats.last_retries
+        )),
+        Line::raw(format!("Recovered {} times", stats.recoveries)),
+        Line::from(vec![
+            Span::raw("STATUS  "),
+            flag("RX_DR", status.rx_dr),
+            flag("TX_DS", status.tx_ds),
+            flag("MAX_RT", status.max_rt),
+            flag("TX_FULL", status.tx_full),
+            Span::raw(format!("RX_P_NO={}", status.rx_p_no)),
+        ]),
+        Line::from(vec![
+            Span::raw("FIFO    "),
+            flag("TX_REUSE", fifo.tx_reuse),
+            flag("TX_FULL", fifo.tx_full),
+            flag("TX_EMPTY", fifo.tx_empty),
+            flag("RX_FULL", fifo.rx_full),
+            flag("RX_EMPTY", fifo.rx_empty),
+        ]),
+    ];
+    if stats.listening {
+        let heard = format!(
+            "Heard {} frames, {} telemetry",
+            stats.heard_frames, stats.heard_telemetry
+        );
+        lines.insert(4, Line::raw(heard));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Radio ")),
+        area,
+    );
+}
+
+fn flag(name: &'static str, set: bool) -> Span<'static> {
+    let style = if set {
+        Style::new().yellow().bold()
+    } else {
+        Style::new().dark_gray()
+    };
+    Span::styled(format!("{} ", name), style)
+}
+
+fn draw_robot(frame: &mut Frame, area: Rect, stats: &Stats) {
+    let link = if stats.link_up {
+        Span::styled("UP", Style::new().fg(Color::Green).bold())
+    } else {
+        Span::styled("DOWN", Style::new().fg(Color::Red).bold())
+    };
+
+    let mut lines = vec![Line::from(vec![
+        Span::raw("Link "),
+        link,
+        Span::raw(format!("   Loss {:.0}%", stats.loss * 100.0)),
+    ])];
+
+    lines.push(match &stats.telemetry {
+        Some(telemetry) => Line::raw(format!(
+            "Mode {:?}   Battery {:.2}V   Current {:.2}A",
+            telemetry.mode,
+            telemetry.voltage as f32 / 1000.0,
+            telemetry.current as f32 / 1000.0
+        )),
+        None => Line::raw("No telemetry, run with --telemetry").dark_gray(),
+    });
+
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title(" Robot ")),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dashboard::RadioSettings;
+    use crate::rf24::{DataRate, Delivery, PowerLevel};
+    use ratatui::backend::TestBackend;
+
+    fn render(stats: &Stats, log: &VecDeque<String>) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 24)).unwrap();
+        terminal
+            .draw(|frame| draw(frame, stats, 50.0, log))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn shows_the_current_radio_settings() {
+        let mut stats = Stats::new();
+        stats.radio = Some(RadioSettings {
+            channel: 125,
+            data_rate: DataRate::_250Kbps,
+            power_level: PowerLevel::_0dBm,
+        });
+        stats.record_delivery(&Delivery::Lost);
+        let log = VecDeque::from([String::from("Link with the robot is down")]);
+
+        let screen = render(&stats, &log);
+        assert!(screen.contains("Channel 125   Rate 250Kbps   Power 0dBm"));
+        assert!(screen.contains("Sent 1 frames, 50/s"));
+        assert!(screen.contains("Acked 0   Lost 1"));
+        assert!(screen.contains("Link DOWN"));
+        assert!(screen.contains("Link with the robot is down"));
+
+        // A remote command moved the radio
+        stats.radio = Some(RadioSettings {
+            channel: 40,
+            data_rate: DataRate::_1Mbps,
+            power_level: PowerLevel::_18dBm,
+        });
+        assert!(render(&stats, &log).contains("Channel 40   Rate 1Mbps   Power -18dBm"));
+    }
+}
@@ -0,0 +1,102 @@
+use crate::controller::InputError;
+use crate::rf24::RF24Error;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// Everything that can stop the controller application.
+#[derive(Debug)]
+pub enum AppError {
+    Radio(RF24Error),
+    Input(InputError),
+    /// A recording or an output file can't be read or written.
+    File {
+        path: PathBuf,
+        error: io::Error,
+    },
+    /// The terminal can't be set up for the dashboard.
+    Dashboard(io::Error),
+    /// The frames can't be exchanged with the gateway.
+    Network(io::Error),
+}
+
+impl AppError {
+    /// Tags an I/O error with the file it happened on, for `map_err`.
+    pub fn file(path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> AppError {
+        let path = path.into();
+        move |error| AppError::File { path, error }
+    }
+
+    /// Whether the control loop can carry on after the error.
+    ///
+    /// Stopping on a transient radio error would leave the robot to its link timeout while it's
+    /// moving, the next frame will most likely get through. A gateway that is restarting or
+    /// out of reach for a moment is the same.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AppError::Radio(e) => e.is_transient(),
+            AppError::Network(_) => true,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AppError::Radio(e) => write!(f, "radio: {}", e),
+            AppError::Input(e) => write!(f, "input: {}", e),
+            AppError::File { path, error } => write!(f, "{}: {}", path.display(), error),
+            AppError::Dashboard(e) => write!(f, "dashboard: {}", e),
+            AppError::Network(e) => write!(f, "network: {}", e),
+        }
+    }
+}
+
+impl Error for AppError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AppError::Radio(e) => Some(e),
+            AppError::Input(e) => Some(e),
+            AppError::File { error, .. } => Some(error),
+            AppError::Dashboard(e) => Some(e),
+            AppError::Network(e) => Some(e),
+        }
+    }
+}
+
+impl From<RF24Error> for AppError {
+    fn from(error: RF24Error) -> Self {
+        AppError::Radio(error)
+    }
+}
+
+impl From<InputError> for AppError {
+    fn from(error: InputError) -> Self {
+        AppError::Input(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_transient_radio_errors_are_retryable() {
+        let mismatch = RF24Error::RegisterMismatch {
+            register: crate::rf24::Register::CONFIG,
+            written: 0x7E,
+            read: 0x08,
+        };
+        assert!(AppError::from(mismatch).is_retryable());
+        assert!(!AppError::from(RF24Error::ChipNotConnected).is_retryable());
+        assert!(!AppError::from(InputError::Quit).is_retryable());
+
+        let error = AppError::from(RF24Error::InvalidChannel);
+        assert_eq!(
+            error.to_string(),
+            "radio: the channel must be from 0 to 127"
+        );
+    }
+}
@@ -0,0 +1,295 @@
+use crate::controller::{Axis, Button, ControllerState, Input, InputError};
+use evdev::{AbsInfo, AbsoluteAxisCode, Device, EventSummary, InputEvent, KeyCode};
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use std::io;
+use std::path::{Path, PathBuf};
+
+const INPUT_DIR: &str = "/dev/input";
+
+struct Gamepad {
+    device: Device,
+    path: PathBuf,
+    axes: Vec<(AbsoluteAxisCode, AbsInfo)>,
+}
+
+impl Gamepad {
+    /// Opens the event device at `path` if it looks like a gamepad.
+    ///
+    /// Returns `None` for keyboards, mice and any device that can't be opened.
+    fn open(path: &Path) -> Option<Gamepad> {
+        let device = Device::open(path).ok()?;
+
+        let has_buttons = device
+            .supported_keys()
+            .is_some_and(|keys| keys.contains(KeyCode::BTN_SOUTH));
+        let has_sticks = device
+            .supported_absolute_axes()
+            .is_some_and(|axes| axes.contains(AbsoluteAxisCode::ABS_X));
+        if !has_buttons || !has_sticks {
+            return None;
+        }
+
+        device.set_nonblocking(true).ok()?;
+        let axes = device.get_absinfo().ok()?.collect();
+
+        Some(Gamepad {
+            device,
+            path: path.to_path_buf(),
+            axes,
+        })
+    }
+
+    fn name(&self) -> &str {
+        self.device.name().unwrap_or("unknown")
+    }
+
+    fn axis_info(&self, code: AbsoluteAxisCode) -> Option<&AbsInfo> {
+        self.axes
+            .iter()
+            .find(|(axis, _)| *axis == code)
+            .map(|(_, info)| info)
+    }
+}
+
+/// Reads a gamepad straight from the Linux input subsystem, without SDL.
+///
+/// Useful on headless hosts where SDL has no video or event subsystem to work with.
+pub struct EvdevController {
+    state: ControllerState,
+    inotify: Inotify,
+    gamepad: Option<Gamepad>,
+}
+
+impl Input for EvdevController {
+    fn get_state(&self) -> &ControllerState {
+        &self.state
+    }
+
+    /// Drains the pending events of the attached gamepad and updates the controller state.
+    ///
+    /// Gamepads plugged in later are picked up through inotify on `/dev/input`.
+    fn update_state(&mut self) -> Result<(), InputError> {
+        self.handle_hotplug();
+
+        let EvdevController { state, gamepad, .. } = self;
+
+        let Some(pad) = gamepad else {
+            return Ok(());
+        };
+
+        let events: io::Result<Vec<InputEvent>> =
+            pad.device.fetch_events().map(|events| events.collect());
+
+        let events = match events {
+            Ok(events) => events,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(_) => {
+                println!("Joystick detached: {}", pad.path.display());
+                // Don't keep driving the robot with whatever was pressed when it was unplugged.
+                *state = ControllerState::new();
+                *gamepad = None;
+                return Ok(());
+            }
+        };
+
+        for event in events {
+            match event.destructure() {
+                EventSummary::Key(_, code, value) => {
+                    if let Some(button) = map_button(code) {
+                        state.update_button(button, value != 0);
+                    }
+                }
+                EventSummary::AbsoluteAxis(_, code, value) => {
+                    if let (Some(axis), Some(info)) = (map_axis(code), pad.axis_info(code)) {
+                        state.update_axis(axis, scale_axis(axis, value, info));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl EvdevController {
+    /// Opens a gamepad that was created or got its permissions fixed by udev since the last call.
+    fn handle_hotplug(&mut self) {
+        // The inotify descriptor is non-blocking, an error here only means there is nothing to read.
+        let Ok(events) = self.inotify.read_events() else {
+            return;
+        };
+
+        for event in events {
+            let Some(name) = event.name else {
+                continue;
+            };
+            if !name.to_string_lossy().starts_with("event") {
+                continue;
+            }
+
+            let path = Path::new(INPUT_DIR).join(name);
+            if self.gamepad.as_ref().is_some_and(|pad| pad.path == path) {
+                continue;
+            }
+
+            if let Some(pad) = Gamepad::open(&path) {
+                if self.gamepad.is_some() {
+                    println!(
+                        "More than one controller was attached. Only one can be used at a time."
+                    );
+                } else {
+                    println!("Controller attached: {}", pad.name());
+                    self.gamepad = Some(pad);
+                }
+            }
+        }
+    }
+}
+
+fn map_button(code: KeyCode) -> Option<Button> {
+    match code {
+        KeyCode::BTN_SOUTH => Some(Button::A),
+        KeyCode::BTN_EAST => Some(Button::B),
+        KeyCode::BTN_WEST => Some(Button::X),
+        KeyCode::BTN_NORTH => Some(Button::Y),
+        KeyCode::BTN_SELECT => Some(Button::Back),
+        KeyCode::BTN_START => Some(Button::Start),
+        KeyCode::BTN_TL => Some(Button::LeftShoulder),
+        KeyCode::BTN_TR => Some(Button::RightShoulder),
+        _ => None,
+    }
+}
+
+fn map_axis(code: AbsoluteAxisCode) -> Option<Axis> {
+    match code {
+        AbsoluteAxisCode::ABS_X => Some(Axis::LeftX),
+        AbsoluteAxisCode::ABS_Y => Some(Axis::LeftY),
+        AbsoluteAxisCode::ABS_RX => Some(Axis::RightX),
+        AbsoluteAxisCode::ABS_RY => Some(Axis::RightY),
+        AbsoluteAxisCode::ABS_Z | AbsoluteAxisCode::ABS_BRAKE => Some(Axis::TriggerLeft),
+        AbsoluteAxisCode::ABS_RZ | AbsoluteAxisCode::ABS_GAS => Some(Axis::TriggerRight),
+        _ => None,
+    }
+}
+
+/// Scales a raw axis value from the device range to the range SDL would report.
+///
+/// Sticks go from -32_768 to 32_767 and triggers from 0 to 32_767.
+fn scale_axis(axis: Axis, value: i32, info: &AbsInfo) -> i16 {
+    let min = info.minimum() as i64;
+    let max = info.maximum() as i64;
+    if max <= min {
+        return 0;
+    }
+
+    let offset = (value as i64).clamp(min, max) - min;
+    let scaled = match axis {
+        Axis::TriggerLeft | Axis::TriggerRight => offset * 32_767 / (max - min),
+        _ => offset * 65_535 / (max - min) - 32_768,
+    };
+
+    scaled as i16
+}
+
+pub fn init() -> Result<EvdevController, InputError> {
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+        .map_err(InputError::Inotify)?;
+    inotify
+        .add_watch(
+            INPUT_DIR,
+            AddWatchFlags::IN_CREATE | AddWatchFlags::IN_ATTRIB,
+        )
+        .map_err(InputError::Inotify)?;
+
+    let gamepad = evdev::enumerate().find_map(|(path, _)| Gamepad::open(&path));
+    if let Some(pad) = &gamepad {
+        println!("Controller attached: {}", pad.name());
+    }
+
+    Ok(EvdevController {
+        state: ControllerState::new(),
+        inotify,
+        gamepad,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(minimum: i32, maximum: i32) -> AbsInfo {
+        AbsInfo::new(0, minimum, maximum, 0, 0, 0)
+    }
+
+    #[test]
+    fn scales_sticks_to_the_sdl_range() {
+        let info = range(-32_768, 32_767);
+        assert_eq!(scale_axis(Axis::LeftX, -32_768, &info), -32_768);
+        assert_eq!(scale_axis(Axis::LeftX, 0, &info), 0);
+        assert_eq!(scale_axis(Axis::LeftX, 32_767, &info), 32_767);
+
+        // Most gamepads report their sticks as a byte, centered on 128
+        let info = range(0, 255);
+        assert_eq!(scale_axis(Axis::RightY, 0, &info), -32_768);
+        assert_eq!(scale_axis(Axis::RightY, 255, &info), 32_767);
+        assert!(scale_axis(Axis::RightY, 128, &info).abs() < 256);
+
+        // Values outside the range the device announced are clamped
+        assert_eq!(scale_axis(Axis::LeftY, -10, &info), -32_768);
+        assert_eq!(scale_axis(Axis::LeftY, 300, &info), 32_767);
+    }
+
+    #[test]
+    fn scales_triggers_from_zero() {
+        let info = range(0, 1023);
+        assert_eq!(scale_axis(Axis::TriggerLeft, 0, &info), 0);
+        assert_eq!(scale_axis(Axis::TriggerLeft, 1023, &info), 32_767);
+        assert_eq!(scale_axis(Axis::TriggerRight, 2000, &info), 32_767);
+        assert_eq!(scale_axis(Axis::TriggerRight, -5, &info), 0);
+    }
+
+    #[test]
+    fn centers_axes_with_an_empty_or_inverted_range() {
+        for info in [range(0, 0), range(255, 0)] {
+            assert_eq!(scale_axis(Axis::LeftX, 100, &info), 0);
+            assert_eq!(scale_axis(Axis::TriggerLeft, 100, &info), 0);
+        }
+    }
+
+    #[test]
+    fn maps_the_gamepad_axes() {
+        let axes = [
+            (AbsoluteAxisCode::ABS_X, Axis::LeftX),
+            (AbsoluteAxisCode::ABS_Y, Axis::LeftY),
+            (AbsoluteAxisCode::ABS_RX, Axis::RightX),
+            (AbsoluteAxisCode::ABS_RY, Axis::RightY),
+            (AbsoluteAxisCode::ABS_Z, Axis::TriggerLeft),
+            (AbsoluteAxisCode::ABS_BRAKE, Axis::TriggerLeft),
+            (AbsoluteAxisCode::ABS_RZ, Axis::TriggerRight),
+            (AbsoluteAxisCode::ABS_GAS, Axis::TriggerRight),
+        ];
+        for (code, axis) in axes {
+            assert_eq!(map_axis(code), Some(axis), "{:?}", code);
+        }
+        assert_eq!(map_axis(AbsoluteAxisCode::ABS_HAT0X), None);
+    }
+
+    #[test]
+    fn maps_the_gamepad_buttons() {
+        let buttons = [
+            (KeyCode::BTN_SOUTH, Button::A),
+            (KeyCode::BTN_EAST, Button::B),
+            (KeyCode::BTN_WEST, Button::X),
+            (KeyCode::BTN_NORTH, Button::Y),
+            (KeyCode::BTN_SELECT, Button::Back),
+            (KeyCode::BTN_START, Button::Start),
+            (KeyCode::BTN_TL, Button::LeftShoulder),
+            (KeyCode::BTN_TR, Button::RightShoulder),
+        ];
+        for (code, button) in buttons {
+            assert_eq!(map_button(code), Some(button), "{:?}", code);
+        }
+        assert_eq!(map_button(KeyCode::BTN_MODE), None);
+    }
+}
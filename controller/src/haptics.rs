@@ -0,0 +1,139 @@
+use crate::controller::Input;
+use crate::link::LinkEvent;
+use crate::telemetry::{RobotMode, Telemetry};
+use std::time::Duration;
+
+// Battery thresholds for the 2S LiPo pack on the robot, in millivolts.
+const BATTERY_LOW: u16 = 7_200;
+const BATTERY_CRITICAL: u16 = 6_800;
+
+const GREEN: [u8; 3] = [0x00, 0xFF, 0x00];
+const YELLOW: [u8; 3] = [0xFF, 0xA0, 0x00];
+const RED: [u8; 3] = [0xFF, 0x00, 0x00];
+const BLUE: [u8; 3] = [0x00, 0x00, 0xFF];
+
+/// Lets the driver feel the link with the robot through the gamepad.
+///
+/// The gamepad rumbles when packets start to get lost or the link drops, and the light bar
+/// shows the robot battery level, or blue while the robot is in setup mode.
+pub struct Haptics {
+    led: Option<[u8; 3]>,
+}
+
+impl Haptics {
+    pub fn new() -> Self {
+        Haptics { led: None }
+    }
+
+    pub fn handle(&mut self, input: &mut dyn Input, event: &LinkEvent) {
+        match event {
+            LinkEvent::Up => {
+                input.rumble(0x0000, 0x4000, Duration::from_millis(100));
+            }
+            LinkEvent::Down => {
+                input.rumble(0xFFFF, 0xFFFF, Duration::from_millis(600));
+                self.set_led(input, RED);
+            }
+            LinkEvent::LossSpike(_) => {
+                input.rumble(0x8000, 0x0000, Duration::from_millis(200));
+            }
+            LinkEvent::Telemetry(telemetry) => {
+                self.set_led(input, led_color(telemetry));
+            }
+        }
+    }
+
+    /// Only talks to the gamepad when the color changes, telemetry arrives with every ACK.
+    fn set_led(&mut self, input: &mut dyn Input, color: [u8; 3]) {
+        if self.led != Some(color) {
+            self.led = Some(color);
+            input.set_led(color[0], color[1], color[2]);
+        }
+    }
+}
+
+impl Default for Haptics {
+    fn default() -> Self {
+        Haptics::new()
+    }
+}
+
+fn led_color(telemetry: &Telemetry) -> [u8; 3] {
+    if telemetry.mode == RobotMode::Setup {
+        return BLUE;
+    }
+
+    match telemetry.voltage {
+        v if v <= BATTERY_CRITICAL => RED,
+        v if v <= BATTERY_LOW => YELLOW,
+        _ => GREEN,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::{ControllerState, InputError};
+
+    /// Records the colors the light bar is set to.
+    struct Gamepad {
+        state: ControllerState,
+        leds: Vec<[u8; 3]>,
+    }
+
+    impl Input for Gamepad {
+        fn update_state(&mut self) -> Result<(), InputError> {
+            Ok(())
+        }
+
+        fn get_state(&self) -> &ControllerState {
+            &self.state
+        }
+
+        fn set_led(&mut self, red: u8, green: u8, blue: u8) {
+            self.leds.push([red, green, blue]);
+        }
+    }
+
+    fn telemetry(mode: RobotMode, voltage: u16) -> Telemetry {
+        Telemetry {
+            mode,
+            voltage,
+            current: 0,
+        }
+    }
+
+    #[test]
+    fn shows_the_battery_level() {
+        assert_eq!(led_color(&telemetry(RobotMode::Running, 8_000)), GREEN);
+        assert_eq!(led_color(&telemetry(RobotMode::Running, 7_201)), GREEN);
+        assert_eq!(led_color(&telemetry(RobotMode::Running, 7_200)), YELLOW);
+        assert_eq!(led_color(&telemetry(RobotMode::Running, 6_801)), YELLOW);
+        assert_eq!(led_color(&telemetry(RobotMode::Running, 6_800)), RED);
+        assert_eq!(led_color(&telemetry(RobotMode::Running, 0)), RED);
+    }
+
+    #[test]
+    fn shows_the_setup_mode_whatever_the_battery() {
+        assert_eq!(led_color(&telemetry(RobotMode::Setup, 8_000)), BLUE);
+        assert_eq!(led_color(&telemetry(RobotMode::Setup, 6_000)), BLUE);
+    }
+
+    #[test]
+    fn only_sets_the_led_when_the_color_changes() {
+        let mut gamepad = Gamepad {
+            state: ControllerState::new(),
+            leds: Vec::new(),
+        };
+        let mut haptics = Haptics::new();
+        let full = LinkEvent::Telemetry(telemetry(RobotMode::Running, 8_000));
+
+        haptics.handle(&mut gamepad, &full);
+        haptics.handle(&mut gamepad, &full);
+        haptics.handle(&mut gamepad, &LinkEvent::Down);
+        haptics.handle(&mut gamepad, &LinkEvent::Down);
+        haptics.handle(&mut gamepad, &full);
+
+        assert_eq!(gamepad.leds, vec![GREEN, RED, GREEN]);
+    }
+}
@@ -0,0 +1,191 @@
+use crate::rf24::{RF24Error, Radio};
+use std::time::{Duration, Instant};
+
+/// Time between two checks of the radio configuration.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// SPI errors in a row after which the radio is initialized again.
+const MAX_SPI_ERRORS: u32 = 3;
+
+/// Sends failing in a row for other reasons after which the radio is initialized again.
+const MAX_SEND_ERRORS: u32 = 3;
+
+/// Why the radio had to be initialized again.
+#[derive(Debug)]
+pub enum Fault {
+    /// The configuration read back didn't match, usually because the module browned out.
+    Misconfigured(RF24Error),
+    /// Several transfers in a row failed on the bus.
+    SpiErrors(u32),
+    /// Several sends in a row failed for other reasons, usually the module not getting done
+    /// in time.
+    SendErrors(u32),
+}
+
+#[derive(Debug)]
+pub enum HealthEvent {
+    Recovered { fault: Fault, recoveries: u32 },
+    RecoveryFailed { fault: Fault, error: RF24Error },
+}
+
+/// Watches over the radio module and configures it again when it loses its configuration.
+///
+/// The nRF24 resets its registers when it browns out, which happens when the motors start on a
+/// shared supply, and would otherwise keep sending with the wrong settings, or not at all.
+pub struct HealthMonitor {
+    last_check: Instant,
+    spi_errors: u32,
+    send_errors: u32,
+    recoveries: u32,
+}
+
+impl HealthMonitor {
+    pub fn new(now: Instant) -> Self {
+        HealthMonitor {
+            last_check: now,
+            spi_errors: 0,
+            send_errors: 0,
+            recoveries: 0,
+        }
+    }
+
+    /// Number of times the radio has been initialized again.
+    pub fn recoveries(&self) -> u32 {
+        self.recoveries
+    }
+
+    pub fn send_succeeded(&mut self) {
+        self.spi_errors = 0;
+        self.send_errors = 0;
+    }
+
+    /// Counts a failed send, as an SPI error when the bus failed and as a send error otherwise.
+    pub fn send_failed(&mut self, error: &RF24Error) {
+        match error {
+            RF24Error::SpiError(_) | RF24Error::GpioError(_) => self.spi_errors += 1,
+            _ => self.send_errors += 1,
+        }
+    }
+
+    /// Initializes the radio again if it's misconfigured or keeps failing.
+    ///
+    /// The configuration is only read back every `CHECK_INTERVAL`, to keep the SPI bus free for
+    /// the payloads.
+    pub fn check(&mut self, radio: &mut Radio, now: Instant) -> Option<HealthEvent> {
+        let fault = if self.spi_errors >= MAX_SPI_ERRORS {
+            Fault::SpiErrors(self.spi_errors)
+        } else if self.send_errors >= MAX_SEND_ERRORS {
+            Fault::SendErrors(self.send_errors)
+        } else if now.duration_since(self.last_check) >= CHECK_INTERVAL {
+            self.last_check = now;
+            match radio.verify_configuration() {
+                Ok(()) => return None,
+                Err(RF24Error::SpiError(_)) => {
+                    self.spi_errors += 1;
+                    return None;
+                }
+                Err(e) => Fault::Misconfigured(e),
+            }
+        } else {
+            return None;
+        };
+
+        self.spi_errors = 0;
+        self.send_errors = 0;
+        self.last_check = now;
+
+        match radio.reinitialize() {
+            Ok(()) => {
+                self.recoveries += 1;
+                Some(HealthEvent::Recovered {
+                    fault,
+                    recoveries: self.recoveries,
+                })
+            }
+            Err(error) => Some(HealthEvent::RecoveryFailed { fault, error }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rf24::mock::{configured_radio, MockBus, MockChip};
+    use crate::rf24::Register;
+
+    #[test]
+    fn reconfigures_a_radio_that_browned_out() {
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        let start = Instant::now();
+        let mut health = HealthMonitor::new(start);
+
+        assert!(health.check(&mut radio, start + CHECK_INTERVAL).is_none());
+
+        *bus.chip.lock().unwrap() = MockChip::new();
+        assert!(health.check(&mut radio, start + CHECK_INTERVAL).is_none());
+
+        let event = health.check(&mut radio, start + CHECK_INTERVAL * 2);
+        assert!(matches!(
+            event,
+            Some(HealthEvent::Recovered {
+                fault: Fault::Misconfigured(RF24Error::RegisterMismatch {
+                    register: Register::CONFIG,
+                    ..
+                }),
+                recoveries: 1,
+            })
+        ));
+        assert!(radio.verify_configuration().is_ok());
+        assert_eq!(bus.chip.lock().unwrap().addresses[6], *b"aaaaa");
+        assert_eq!(health.recoveries(), 1);
+    }
+
+    #[test]
+    fn reconfigures_after_repeated_spi_errors() {
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        let now = Instant::now();
+        let mut health = HealthMonitor::new(now);
+
+        let spi_error = || RF24Error::SpiError("transfer failed".into());
+
+        health.send_failed(&spi_error());
+        health.send_failed(&spi_error());
+        health.send_succeeded();
+        health.send_failed(&spi_error());
+        health.send_failed(&spi_error());
+        assert!(health.check(&mut radio, now).is_none());
+
+        health.send_failed(&spi_error());
+        assert!(matches!(
+            health.check(&mut radio, now),
+            Some(HealthEvent::Recovered {
+                fault: Fault::SpiErrors(MAX_SPI_ERRORS),
+                recoveries: 1,
+            })
+        ));
+    }
+
+    #[test]
+    fn tells_timeouts_from_spi_errors() {
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        let now = Instant::now();
+        let mut health = HealthMonitor::new(now);
+
+        health.send_failed(&RF24Error::Timeout("the transmission"));
+        health.send_failed(&RF24Error::SpiError("transfer failed".into()));
+        health.send_failed(&RF24Error::Timeout("the transmission"));
+        assert!(health.check(&mut radio, now).is_none());
+
+        health.send_failed(&RF24Error::Timeout("the transmission"));
+        assert!(matches!(
+            health.check(&mut radio, now),
+            Some(HealthEvent::Recovered {
+                fault: Fault::SendErrors(MAX_SEND_ERRORS),
+                recoveries: 1,
+            })
+        ));
+    }
+}
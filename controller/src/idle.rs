@@ -0,0 +1,119 @@
+use crate::controller::ControllerState;
+use crate::rf24::PowerState;
+use std::time::{Duration, Instant};
+
+/// Time the gamepad has to stay neutral before the controller only sends keep-alives.
+const STANDBY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Time the gamepad has to stay neutral before the radio is powered down.
+const POWER_DOWN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Time between the frames sent in standby. Under `RADIO_TIMEOUT` on the robot, so it
+/// doesn't think the link is lost.
+const KEEPALIVE_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What the control loop does with the radio while the gamepad is idle.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct IdleDecision {
+    pub power_state: PowerState,
+    /// Whether to send the current frame.
+    pub send: bool,
+}
+
+/// Saves power while nobody is driving, for controllers running on batteries.
+///
+/// After a few seconds without input the radio stays in standby and only wakes up to send a
+/// keep-alive now and then, so the robot stays connected. After a minute it's powered down and
+/// nothing is sent, which the robot sees as a lost link. Any input brings it back right away.
+pub struct IdlePolicy {
+    neutral_since: Option<Instant>,
+    last_sent: Option<Instant>,
+}
+
+impl IdlePolicy {
+    pub fn new() -> Self {
+        IdlePolicy {
+            neutral_since: None,
+            last_sent: None,
+        }
+    }
+
+    pub fn update(&mut self, state: &ControllerState, now: Instant) -> IdleDecision {
+        if !state.is_neutral() {
+            self.neutral_since = None;
+        }
+        let neutral_for = now.duration_since(*self.neutral_since.get_or_insert(now));
+
+        let decision = if neutral_for >= POWER_DOWN_TIMEOUT {
+            IdleDecision {
+                power_state: PowerState::PowerDown,
+                send: false,
+            }
+        } else if neutral_for >= STANDBY_TIMEOUT {
+            let keepalive = self
+                .last_sent
+                .is_none_or(|last_sent| now.duration_since(last_sent) >= KEEPALIVE_INTERVAL);
+            IdleDecision {
+                power_state: PowerState::StandbyI,
+                send: keepalive,
+            }
+        } else {
+            IdleDecision {
+                power_state: PowerState::StandbyI,
+                send: true,
+            }
+        };
+
+        if decision.send {
+            self.last_sent = Some(now);
+        }
+
+        decision
+    }
+}
+
+impl Default for IdlePolicy {
+    fn default() -> Self {
+        IdlePolicy::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_less_and_powers_down_while_idle() {
+        let mut idle = IdlePolicy::new();
+        let neutral = ControllerState::new();
+        let start = Instant::now();
+        let at = |millis| start + Duration::from_millis(millis);
+
+        assert!(idle.update(&neutral, start).send);
+        assert!(idle.update(&neutral, at(4_999)).send);
+
+        // Standby, with a keep-alive every 500ms
+        assert!(!idle.update(&neutral, at(5_000)).send);
+        assert!(!idle.update(&neutral, at(5_200)).send);
+        let keepalive = idle.update(&neutral, at(5_499));
+        assert_eq!(keepalive.power_state, PowerState::StandbyI);
+        assert!(keepalive.send);
+
+        assert_eq!(
+            idle.update(&neutral, at(60_000)),
+            IdleDecision {
+                power_state: PowerState::PowerDown,
+                send: false,
+            }
+        );
+
+        let mut driving = ControllerState::new();
+        driving.throttle = -20_000;
+        assert!(idle.update(&driving, at(60_010)).send);
+        assert!(idle.update(&neutral, at(60_020)).send);
+        assert_eq!(
+            idle.update(&neutral, at(64_000)).power_state,
+            PowerState::StandbyI
+        );
+    }
+}
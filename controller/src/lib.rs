@@ -0,0 +1,34 @@
+//! The Robotoy controller: the nRF24L01+ driver, the radio protocol of the robot and the
+//! gamepad inputs, for the `rpi-controller` binary and other tools.
+//!
+//! The protocol builds anywhere. The Raspberry Pi wiring of the radio needs the `rppal`
+//! feature, and SDL gamepads the `sdl2` feature, both on by default. The evdev gamepads and the
+//! serial dongle are only built on Linux, and the dashboard on Unix.
+
+pub mod channel;
+pub mod controller;
+pub mod dashboard;
+pub mod error;
+#[cfg(target_os = "linux")]
+pub mod evdev_input;
+pub mod haptics;
+pub mod health;
+pub mod idle;
+pub mod link;
+pub mod listener;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
+pub mod pcap;
+pub mod protocol;
+pub mod recording;
+pub mod rf24;
+pub mod scan;
+pub mod script;
+#[cfg(feature = "sdl2")]
+pub mod sdl_input;
+#[cfg(target_os = "linux")]
+pub mod serial;
+pub mod telemetry;
+pub mod tilt;
+pub mod transport;
+pub mod web;
@@ -0,0 +1,199 @@
+use crate::rf24::Delivery;
+use crate::telemetry::Telemetry;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// Number of recent payloads the loss ratio is computed over.
+const LOSS_WINDOW: usize = 50;
+
+/// Loss ratio that counts as a spike. It has to go back under half of it before another spike.
+const LOSS_SPIKE: f32 = 0.3;
+
+/// Time without any ACK after which the link is down. Matches `RADIO_TIMEOUT` on the robot.
+const LINK_TIMEOUT: Duration = Duration::from_millis(1000);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LinkEvent {
+    Up,
+    Down,
+    LossSpike(f32),
+    Telemetry(Telemetry),
+}
+
+/// Follows the delivery of the sent payloads and reports the changes in the link with the robot.
+///
+/// It only has something to say when ACK payloads are enabled.
+pub struct LinkMonitor {
+    deliveries: VecDeque<bool>,
+    last_ack: Option<Instant>,
+    up: bool,
+    spiking: bool,
+}
+
+impl LinkMonitor {
+    pub fn new() -> Self {
+        LinkMonitor {
+            deliveries: VecDeque::with_capacity(LOSS_WINDOW),
+            last_ack: None,
+            up: false,
+            spiking: false,
+        }
+    }
+
+    pub fn is_up(&self) -> bool {
+        self.up
+    }
+
+    /// Ratio of the recent payloads that weren't acknowledged, from 0 to 1.
+    pub fn loss(&self) -> f32 {
+        if self.deliveries.is_empty() {
+            return 0.0;
+        }
+        let lost = self.deliveries.iter().filter(|acked| !**acked).count();
+        lost as f32 / self.deliveries.len() as f32
+    }
+
+    pub fn update(&mut self, delivery: &Delivery, now: Instant) -> Vec<LinkEvent> {
+        let mut events = Vec::new();
+
+        let acked = match delivery {
+            Delivery::Unacknowledged => return events,
+            Delivery::Acked { payload, .. } => {
+                if let Some(telemetry) = payload.as_deref().and_then(Telemetry::from_bytes) {
+                    events.push(LinkEvent::Telemetry(telemetry));
+                }
+                true
+            }
+            Delivery::Lost => false,
+        };
+
+        if self.deliveries.len() == LOSS_WINDOW {
+            self.deliveries.pop_front();
+        }
+        self.deliveries.push_back(acked);
+
+        if acked {
+            self.last_ack = Some(now);
+            if !self.up {
+                self.up = true;
+                self.spiking = false;
+                self.deliveries.clear();
+                self.deliveries.push_back(true);
+                events.insert(0, LinkEvent::Up);
+            }
+        } else if self.up {
+            let timed_out = self
+                .last_ack
+                .is_none_or(|last_ack| now.duration_since(last_ack) >= LINK_TIMEOUT);
+            if timed_out {
+                self.up = false;
+                events.push(LinkEvent::Down);
+                return events;
+            }
+        }
+
+        let loss = self.loss();
+        if self.up && !self.spiking && self.deliveries.len() == LOSS_WINDOW && loss >= LOSS_SPIKE {
+            self.spiking = true;
+            events.push(LinkEvent::LossSpike(loss));
+        } else if self.spiking && loss < LOSS_SPIKE / 2.0 {
+            self.spiking = false;
+        }
+
+        events
+    }
+}
+
+impl Default for LinkMonitor {
+    fn default() -> Self {
+        LinkMonitor::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::telemetry::RobotMode;
+
+    fn acked() -> Delivery {
+        Delivery::Acked {
+            retries: 0,
+            payload: None,
+        }
+    }
+
+    #[test]
+    fn reports_the_link_going_up_and_down() {
+        let mut monitor = LinkMonitor::new();
+        let start = Instant::now();
+
+        assert_eq!(monitor.update(&Delivery::Lost, start), vec![]);
+        assert_eq!(monitor.update(&acked(), start), vec![LinkEvent::Up]);
+        assert_eq!(monitor.update(&acked(), start), vec![]);
+
+        let almost = start + LINK_TIMEOUT - Duration::from_millis(1);
+        assert_eq!(monitor.update(&Delivery::Lost, almost), vec![]);
+        assert!(monitor.is_up());
+
+        let timeout = start + LINK_TIMEOUT;
+        assert_eq!(
+            monitor.update(&Delivery::Lost, timeout),
+            vec![LinkEvent::Down]
+        );
+        assert!(!monitor.is_up());
+    }
+
+    #[test]
+    fn reports_a_loss_spike_once() {
+        let mut monitor = LinkMonitor::new();
+        let now = Instant::now();
+
+        for _ in 0..LOSS_WINDOW {
+            monitor.update(&acked(), now);
+        }
+
+        let mut spikes = 0;
+        for i in 0..LOSS_WINDOW {
+            let delivery = if i % 2 == 0 { Delivery::Lost } else { acked() };
+            for event in monitor.update(&delivery, now) {
+                if let LinkEvent::LossSpike(loss) = event {
+                    assert!(loss >= LOSS_SPIKE);
+                    spikes += 1;
+                }
+            }
+        }
+
+        assert_eq!(spikes, 1);
+    }
+
+    #[test]
+    fn decodes_the_telemetry_in_the_ack() {
+        let mut monitor = LinkMonitor::new();
+        let delivery = Delivery::Acked {
+            retries: 1,
+            payload: Some(vec![0x01, 0x1C, 0xE8, 0xFF, 0x9C]),
+        };
+
+        assert_eq!(
+            monitor.update(&delivery, Instant::now()),
+            vec![
+                LinkEvent::Up,
+                LinkEvent::Telemetry(Telemetry {
+                    mode: RobotMode::Running,
+                    voltage: 7400,
+                    current: -100,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_unacknowledged_payloads() {
+        let mut monitor = LinkMonitor::new();
+        assert_eq!(
+            monitor.update(&Delivery::Unacknowledged, Instant::now()),
+            vec![]
+        );
+        assert_eq!(monitor.loss(), 0.0);
+    }
+}
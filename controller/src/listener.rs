@@ -0,0 +1,178 @@
+use crate::rf24::{RF24Error, Radio, PAYLOAD_SIZE};
+use crate::telemetry::{Telemetry, TELEMETRY_SIZE};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::{self, sleep, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Time between two reads of the RX FIFO while it's empty.
+const POLL_INTERVAL: Duration = Duration::from_micros(200);
+
+/// A payload heard by the listening radio.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Packet {
+    /// A controller state or control frame, sent by the other radio.
+    Frame(Vec<u8>),
+    /// The telemetry the robot attached to an ACK.
+    Telemetry(Telemetry),
+    Other(Vec<u8>),
+}
+
+impl Packet {
+    fn from_payload(payload: Vec<u8>) -> Packet {
+        match payload.len() {
+            len if len == PAYLOAD_SIZE as usize => Packet::Frame(payload),
+            TELEMETRY_SIZE => match Telemetry::from_bytes(&payload) {
+                Some(telemetry) => Packet::Telemetry(telemetry),
+                None => Packet::Other(payload),
+            },
+            _ => Packet::Other(payload),
+        }
+    }
+}
+
+/// A packet, with the time it was heard on the clock of the session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heard {
+    pub at: Duration,
+    pub packet: Packet,
+}
+
+/// A second radio that listens on the channel while the first one sends, on its own thread.
+///
+/// It hears the frames going out and the ACKs coming back without acknowledging anything,
+/// so the sending radio never has to turn around to receive. Packets are timestamped against
+/// the clock the control loop uses, and errors are passed on to it to classify.
+pub struct Listener {
+    heard: Receiver<Result<Heard, RF24Error>>,
+    channel: Sender<u8>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Listener {
+    /// Starts listening with a radio set up like the sending one, on the same address.
+    pub fn start(mut radio: Radio, clock: Instant) -> Result<Listener, RF24Error> {
+        radio.set_passive(true);
+        radio.configure()?;
+        radio.start_listening()?;
+
+        let (heard_sender, heard) = mpsc::channel();
+        let (channel, channel_receiver) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = thread::spawn({
+            let stop = stop.clone();
+            move || listen(radio, clock, &heard_sender, &channel_receiver, &stop)
+        });
+
+        Ok(Listener {
+            heard,
+            channel,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Everything heard since the last call.
+    pub fn heard(&self) -> impl Iterator<Item = Result<Heard, RF24Error>> + '_ {
+        self.heard.try_iter()
+    }
+
+    /// Moves to the channel the sending radio moved to.
+    pub fn set_channel(&self, channel: u8) {
+        let _ = self.channel.send(channel);
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn listen(
+    mut radio: Radio,
+    clock: Instant,
+    heard: &Sender<Result<Heard, RF24Error>>,
+    channel: &Receiver<u8>,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        let result = match channel.try_iter().last() {
+            Some(channel) => follow(&mut radio, channel).map(|()| None),
+            None => radio.receive(),
+        };
+
+        match result {
+            Ok(Some(payload)) => {
+                let packet = Packet::from_payload(payload);
+                let _ = heard.send(Ok(Heard {
+                    at: clock.elapsed(),
+                    packet,
+                }));
+            }
+            Ok(None) => sleep(POLL_INTERVAL),
+            Err(e) => {
+                let transient = e.is_transient();
+                let _ = heard.send(Err(e));
+                if !transient {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Changes channel, which the module only does in standby.
+fn follow(radio: &mut Radio, channel: u8) -> Result<(), RF24Error> {
+    radio.stop_listening()?;
+    radio.set_channel(channel)?;
+    radio.start_listening()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rf24::mock::{configured_radio, MockBus};
+    use crate::rf24::Register;
+
+    #[test]
+    fn hears_frames_and_telemetry() {
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        radio.set_ack_payloads(true).unwrap();
+        let clock = Instant::now();
+        let listener = Listener::start(radio, clock).unwrap();
+
+        listener.set_channel(40);
+        bus.chip.lock().unwrap().rx_fifo.extend([
+            vec![0; 13],
+            vec![1, 0x1F, 0x40, 0, 200],
+            vec![7],
+        ]);
+
+        let mut packets = Vec::new();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while packets.len() < 3 && Instant::now() < deadline {
+            packets.extend(listener.heard().map(|heard| heard.unwrap().packet));
+            sleep(Duration::from_millis(1));
+        }
+        assert_eq!(packets.len(), 3, "{:?}", packets);
+        assert_eq!(packets[0], Packet::Frame(vec![0; 13]));
+        assert!(matches!(
+            packets[1],
+            Packet::Telemetry(Telemetry { voltage: 8000, .. })
+        ));
+        assert_eq!(packets[2], Packet::Other(vec![7]));
+
+        drop(listener);
+        let chip = bus.chip.lock().unwrap();
+        assert_eq!(chip.register(Register::EN_AA), 0);
+        assert_eq!(chip.register(Register::RF_CH), 40);
+    }
+}
@@ -0,0 +1,332 @@
+//! A bridge to an MQTT broker, for the lab dashboards, built with the `mqtt` feature.
+//!
+//! Under the topic prefix, the session is published about 10 times a second:
+//!
+//! |Topic|Payload|
+//! |---|:---|
+//! |`<prefix>/state`|The controller state sent to the robot, as JSON|
+//! |`<prefix>/link`|Frame counts, loss, channel and whether the link is up, as JSON|
+//! |`<prefix>/telemetry`|The latest telemetry of the robot, as JSON, when there is some|
+//!
+//! `<prefix>/command` takes text commands, see `RemoteCommand`.
+
+use crate::controller::ControllerState;
+use crate::dashboard::Stats;
+use crate::scan::CHANNELS;
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use serde_json::json;
+use std::fmt;
+use std::sync::mpsc::{self, Receiver};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+/// Default port of MQTT brokers.
+pub const DEFAULT_PORT: u16 = 1883;
+
+/// Time between two publications of the session.
+const PUBLISH_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time to wait before connecting again to a broker that went away.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Requests the client can queue before publications are dropped.
+const QUEUE_SIZE: usize = 32;
+
+/// A command received on the command topic.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RemoteCommand {
+    /// `stop`: sends a neutral state, whatever the gamepad does, until `resume`.
+    Stop,
+    /// `resume`: gives the control back to the gamepad.
+    Resume,
+    /// `speed-limit <percent>`: caps the triggers, which set the speed of the robot.
+    SpeedLimit(u8),
+    /// `channel <channel>`: tells the robot to move to the channel, and moves too.
+    Channel(u8),
+}
+
+impl fmt::Display for RemoteCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RemoteCommand::Stop => write!(f, "stop"),
+            RemoteCommand::Resume => write!(f, "resume"),
+            RemoteCommand::SpeedLimit(percent) => write!(f, "speed-limit {}", percent),
+            RemoteCommand::Channel(channel) => write!(f, "channel {}", channel),
+        }
+    }
+}
+
+impl RemoteCommand {
+    pub fn parse(text: &str) -> Result<RemoteCommand, String> {
+        let mut words = text.split_whitespace();
+        let command = match (words.next(), words.next()) {
+            (Some("stop"), None) => RemoteCommand::Stop,
+            (Some("resume"), None) => RemoteCommand::Resume,
+            (Some("speed-limit"), Some(percent)) => match percent.parse() {
+                Ok(percent @ 0..=100) => RemoteCommand::SpeedLimit(percent),
+                _ => return Err(String::from("the speed limit goes from 0 to 100")),
+            },
+            (Some("channel"), Some(channel)) => match channel.parse() {
+                Ok(channel) if (channel as usize) < CHANNELS => RemoteCommand::Channel(channel),
+                _ => return Err(format!("the channel goes from 0 to {}", CHANNELS - 1)),
+            },
+            _ => return Err(format!("unknown command \"{}\"", text.trim())),
+        };
+
+        match words.next() {
+            None => Ok(command),
+            Some(_) => Err(format!("unknown command \"{}\"", text.trim())),
+        }
+    }
+}
+
+/// What happened on the bridge since the last update, for the caller to report.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BridgeEvent {
+    /// The broker accepted the connection, again after a loss.
+    Connected,
+    /// The broker can't be reached, the client keeps trying.
+    Unreachable(String),
+    /// A command was received and applied. `Channel` is left to the caller.
+    Command(RemoteCommand),
+    /// Something that isn't a command was received on the command topic, with the reason.
+    InvalidCommand(String),
+}
+
+/// The limits set by the remote commands.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RemoteControl {
+    pub stopped: bool,
+    /// Percentage of the full speed allowed.
+    pub speed_limit: u8,
+}
+
+impl RemoteControl {
+    pub fn new() -> Self {
+        RemoteControl {
+            stopped: false,
+            speed_limit: 100,
+        }
+    }
+
+    /// Takes a command, returning the channel to move to for `Channel`.
+    pub fn handle(&mut self, command: RemoteCommand) -> Option<u8> {
+        match command {
+            RemoteCommand::Stop => self.stopped = true,
+            RemoteCommand::Resume => self.stopped = false,
+            RemoteCommand::SpeedLimit(percent) => self.speed_limit = percent,
+            RemoteCommand::Channel(channel) => return Some(channel),
+        }
+        None
+    }
+
+    /// Applies the limits to the state about to be sent.
+    pub fn limit(&self, state: &mut ControllerState) {
+        if self.stopped {
+            *state = ControllerState::new();
+            return;
+        }
+        state.l2 = (state.l2 as u32 * self.speed_limit as u32 / 100) as u16;
+        state.r2 = (state.r2 as u32 * self.speed_limit as u32 / 100) as u16;
+    }
+}
+
+impl Default for RemoteControl {
+    fn default() -> Self {
+        RemoteControl::new()
+    }
+}
+
+/// Publishes the session and takes the remote commands, on its own thread.
+///
+/// The broker may come and go, the control loop never waits for it: publications are dropped
+/// while the client can't keep up, and the client connects again on its own.
+pub struct Bridge {
+    client: Client,
+    prefix: String,
+    events: Receiver<BridgeEvent>,
+    control: RemoteControl,
+    last_publish: Option<Instant>,
+}
+
+impl Bridge {
+    pub fn connect(host: &str, port: u16, prefix: &str) -> Bridge {
+        let mut options =
+            MqttOptions::new(format!("rpi-controller-{}", std::process::id()), host, port);
+        options.set_keep_alive(Duration::from_secs(5));
+        let (client, mut connection) = Client::new(options, QUEUE_SIZE);
+        let command_topic = format!("{}/command", prefix);
+        let (sender, events) = mpsc::channel();
+
+        thread::spawn({
+            let client = client.clone();
+            move || {
+                for event in connection.iter() {
+                    let event = match event {
+                        // The session isn't kept by the broker, subscribe again on each connection
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            let _ = client.try_subscribe(command_topic.as_str(), QoS::AtLeastOnce);
+                            BridgeEvent::Connected
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish))) => {
+                            match RemoteCommand::parse(&String::from_utf8_lossy(&publish.payload)) {
+                                Ok(command) => BridgeEvent::Command(command),
+                                Err(e) => BridgeEvent::InvalidCommand(e),
+                            }
+                        }
+                        Ok(_) => continue,
+                        Err(e) => {
+                            sleep(RECONNECT_DELAY);
+                            BridgeEvent::Unreachable(e.to_string())
+                        }
+                    };
+                    // The bridge is gone, the controller is shutting down
+                    if sender.send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+
+        Bridge {
+            client,
+            prefix: prefix.to_string(),
+            events,
+            control: RemoteControl::new(),
+            last_publish: None,
+        }
+    }
+
+    /// Applies the commands received since the last call, and returns what happened.
+    ///
+    /// Moving to another channel is up to the caller, on `Command(RemoteCommand::Channel)`.
+    pub fn update(&mut self) -> Vec<BridgeEvent> {
+        let events: Vec<BridgeEvent> = self.events.try_iter().collect();
+        for event in &events {
+            if let BridgeEvent::Command(command) = event {
+                self.control.handle(*command);
+            }
+        }
+        events
+    }
+
+    /// Applies the remote limits to the state about to be sent.
+    pub fn limit(&self, state: &mut ControllerState) {
+        self.control.limit(state);
+    }
+
+    /// Publishes the session, at most every `PUBLISH_INTERVAL`.
+    pub fn publish(&mut self, stats: &Stats) {
+        if self
+            .last_publish
+            .is_some_and(|last| last.elapsed() < PUBLISH_INTERVAL)
+        {
+            return;
+        }
+        self.last_publish = Some(Instant::now());
+
+        let link = json!({
+            "frames": stats.frames,
+            "acked": stats.acked,
+            "lost": stats.lost,
+            "retries": stats.retries,
+            "recoveries": stats.recoveries,
+            "loss": stats.loss,
+            "link_up": stats.link_up,
+            "channel": stats.radio.map(|radio| radio.channel),
+            "stopped": self.control.stopped,
+            "speed_limit": self.control.speed_limit,
+        });
+        self.send("state", json!(stats.state).to_string());
+        self.send("link", link.to_string());
+        if let Some(telemetry) = stats.telemetry {
+            self.send("telemetry", json!(telemetry).to_string());
+        }
+    }
+
+    fn send(&self, topic: &str, payload: String) {
+        // A full queue means the broker is away, the next publication will be fresher anyway
+        let _ = self.client.try_publish(
+            format!("{}/{}", self.prefix, topic),
+            QoS::AtMostOnce,
+            false,
+            payload,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{switch_frame, ChannelManager};
+    use crate::rf24::mock::{configured_radio, MockBus};
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(RemoteCommand::parse("stop\n"), Ok(RemoteCommand::Stop));
+        assert_eq!(RemoteCommand::parse("resume"), Ok(RemoteCommand::Resume));
+        assert_eq!(
+            RemoteCommand::parse("speed-limit 40"),
+            Ok(RemoteCommand::SpeedLimit(40))
+        );
+        assert_eq!(
+            RemoteCommand::parse("channel 76"),
+            Ok(RemoteCommand::Channel(76))
+        );
+        assert!(RemoteCommand::parse("speed-limit 140").is_err());
+        assert!(RemoteCommand::parse("channel 126").is_err());
+        assert!(RemoteCommand::parse("stop now").is_err());
+        assert!(RemoteCommand::parse("jump").is_err());
+
+        for text in ["stop", "resume", "speed-limit 40", "channel 76"] {
+            assert_eq!(RemoteCommand::parse(text).unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn limits_the_state() {
+        let mut control = RemoteControl::new();
+        let mut state = ControllerState::new();
+        state.r2 = 30_000;
+        state.yaw = -8000;
+
+        assert_eq!(control.handle(RemoteCommand::SpeedLimit(50)), None);
+        let mut limited = state;
+        control.limit(&mut limited);
+        assert_eq!(limited.r2, 15_000);
+        assert_eq!(limited.yaw, -8000);
+
+        control.handle(RemoteCommand::Stop);
+        let mut limited = state;
+        control.limit(&mut limited);
+        assert!(limited.is_neutral());
+
+        control.handle(RemoteCommand::Resume);
+        assert_eq!(control.handle(RemoteCommand::Channel(40)), Some(40));
+        let mut limited = state;
+        control.limit(&mut limited);
+        assert_eq!(limited.r2, 15_000);
+    }
+
+    #[test]
+    fn channel_changes_hold_without_acks() {
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        let start = Instant::now();
+        // What the controller does when no channel option created a manager
+        let mut channels =
+            ChannelManager::new(radio.channel(), Vec::new(), radio.ack_payloads(), start);
+
+        let command = RemoteCommand::parse("channel 40").unwrap();
+        let channel = RemoteControl::new().handle(command).unwrap();
+        let action = channels.prefer(channel);
+        channels.apply(action, &mut radio, start).unwrap();
+        assert_eq!(radio.channel(), 40);
+        assert_eq!(bus.transmitted()[0], switch_frame(40));
+
+        // Well past the time the link has to come up on the new channel
+        let later = start + Duration::from_secs(10);
+        assert_eq!(channels.update(&[], false, later), None);
+        assert_eq!(radio.channel(), 40);
+    }
+}
@@ -0,0 +1,76 @@
+//! Packet captures in the classic pcap format, which Wireshark and tcpdump both read.
+//!
+//! The packets use the first link type reserved for private use, the dissector generated by
+//! `protocol::lua_dissector` is registered for it.
+
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MAGIC: u32 = 0xa1b2_c3d4;
+const VERSION: (u16, u16) = (2, 4);
+/// Longest packet kept whole, the radio payloads are at most 32 bytes.
+const SNAPLEN: u32 = 256;
+/// LINKTYPE_USER0.
+pub const LINKTYPE_USER0: u32 = 147;
+
+pub struct PcapWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Writes the file header.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&MAGIC.to_le_bytes())?;
+        writer.write_all(&VERSION.0.to_le_bytes())?;
+        writer.write_all(&VERSION.1.to_le_bytes())?;
+        // Timestamps are in UTC and their accuracy isn't known.
+        writer.write_all(&0i32.to_le_bytes())?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        writer.write_all(&SNAPLEN.to_le_bytes())?;
+        writer.write_all(&LINKTYPE_USER0.to_le_bytes())?;
+        writer.flush()?;
+
+        Ok(PcapWriter { writer })
+    }
+
+    /// Writes a packet captured at `time`.
+    ///
+    /// Each packet is flushed, so the capture can be followed live with `tail -f`.
+    pub fn write_packet(&mut self, time: SystemTime, packet: &[u8]) -> io::Result<()> {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let length = packet.len() as u32;
+        let captured = length.min(SNAPLEN);
+
+        self.writer
+            .write_all(&(since_epoch.as_secs() as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&since_epoch.subsec_micros().to_le_bytes())?;
+        self.writer.write_all(&captured.to_le_bytes())?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&packet[..captured as usize])?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn writes_headers_and_packets() {
+        let mut pcap = PcapWriter::new(Vec::new()).unwrap();
+        let time = UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456);
+        pcap.write_packet(time, &[125, 1, 2, 3]).unwrap();
+
+        let bytes = pcap.writer;
+        assert_eq!(bytes.len(), 24 + 16 + 4);
+        assert_eq!(bytes[..4], [0xd4, 0xc3, 0xb2, 0xa1]);
+        assert_eq!(bytes[20..24], 147u32.to_le_bytes());
+        assert_eq!(bytes[24..28], 1_700_000_000u32.to_le_bytes());
+        assert_eq!(bytes[28..32], 123_456u32.to_le_bytes());
+        assert_eq!(bytes[32..36], 4u32.to_le_bytes());
+        assert_eq!(bytes[36..40], 4u32.to_le_bytes());
+        assert_eq!(bytes[40..], [125, 1, 2, 3]);
+    }
+}
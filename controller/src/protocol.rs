@@ -0,0 +1,305 @@
+use crate::channel::{CONTROL_MARKER, SWITCH_CHANNEL};
+use crate::rf24::PAYLOAD_SIZE;
+use crate::telemetry::TELEMETRY_SIZE;
+use std::fmt::Write as _;
+
+/// How a field of a payload is encoded. Numbers are big-endian.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum FieldType {
+    /// One bit per name, from the least significant.
+    Flags(&'static [&'static str]),
+    U8,
+    U16,
+    I16,
+}
+
+impl FieldType {
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::Flags(_) | FieldType::U8 => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Field {
+    pub name: &'static str,
+    pub offset: usize,
+    pub field_type: FieldType,
+}
+
+impl Field {
+    const fn new(name: &'static str, offset: usize, field_type: FieldType) -> Field {
+        Field {
+            name,
+            offset,
+            field_type,
+        }
+    }
+
+    /// The value of the field in `payload`, written the way it's printed.
+    fn format(&self, payload: &[u8]) -> String {
+        let bytes = &payload[self.offset..self.offset + self.field_type.size()];
+        match self.field_type {
+            FieldType::Flags(names) => {
+                let set: Vec<&str> = names
+                    .iter()
+                    .enumerate()
+                    .filter(|(bit, _)| bytes[0] & (1 << bit) != 0)
+                    .map(|(_, name)| *name)
+                    .collect();
+                format!("[{}]", set.join(" "))
+            }
+            FieldType::U8 => bytes[0].to_string(),
+            FieldType::U16 => u16::from_be_bytes([bytes[0], bytes[1]]).to_string(),
+            FieldType::I16 => i16::from_be_bytes([bytes[0], bytes[1]]).to_string(),
+        }
+    }
+}
+
+/// The controller state frame, see the Protocol section of the README.
+pub const CONTROLLER_STATE: [Field; 7] = [
+    Field::new(
+        "buttons",
+        0,
+        FieldType::Flags(&[
+            "x", "circle", "square", "triangle", "select", "start", "l1", "r1",
+        ]),
+    ),
+    Field::new("l2", 1, FieldType::U16),
+    Field::new("r2", 3, FieldType::U16),
+    Field::new("yaw", 5, FieldType::I16),
+    Field::new("throttle", 7, FieldType::I16),
+    Field::new("pitch", 9, FieldType::I16),
+    Field::new("roll", 11, FieldType::I16),
+];
+
+/// A control frame, told apart from the controller state by its marker.
+pub const CONTROL: [Field; 3] = [
+    Field::new("marker", 1, FieldType::U16),
+    Field::new("message", 3, FieldType::U8),
+    Field::new("channel", 4, FieldType::U8),
+];
+
+/// The telemetry the robot attaches to the ACKs.
+pub const TELEMETRY: [Field; 3] = [
+    Field::new("mode", 0, FieldType::U8),
+    Field::new("voltage", 1, FieldType::U16),
+    Field::new("current", 3, FieldType::I16),
+];
+
+/// The kinds of payloads on air, told apart by their size and the control marker.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PayloadKind {
+    ControllerState,
+    Control,
+    Telemetry,
+    Unknown,
+}
+
+impl PayloadKind {
+    pub fn of(payload: &[u8]) -> PayloadKind {
+        match payload.len() {
+            len if len == PAYLOAD_SIZE as usize && payload[1..3] == CONTROL_MARKER => {
+                PayloadKind::Control
+            }
+            len if len == PAYLOAD_SIZE as usize => PayloadKind::ControllerState,
+            TELEMETRY_SIZE => PayloadKind::Telemetry,
+            _ => PayloadKind::Unknown,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PayloadKind::ControllerState => "state",
+            PayloadKind::Control => "control",
+            PayloadKind::Telemetry => "telemetry",
+            PayloadKind::Unknown => "unknown",
+        }
+    }
+
+    pub fn fields(&self) -> &'static [Field] {
+        match self {
+            PayloadKind::ControllerState => &CONTROLLER_STATE,
+            PayloadKind::Control => &CONTROL,
+            PayloadKind::Telemetry => &TELEMETRY,
+            PayloadKind::Unknown => &[],
+        }
+    }
+}
+
+/// A one line description of a payload, like `state buttons=[x] l2=0 ... roll=-120`.
+pub fn describe(payload: &[u8]) -> String {
+    let kind = PayloadKind::of(payload);
+    let mut description = String::from(kind.name());
+
+    match kind {
+        PayloadKind::Control if payload[3] == SWITCH_CHANNEL => {
+            let _ = write!(description, " switch channel={}", payload[4]);
+        }
+        PayloadKind::Unknown => {
+            for byte in payload {
+                let _ = write!(description, " {:02x}", byte);
+            }
+        }
+        _ => {
+            for field in kind.fields() {
+                let _ = write!(description, " {}={}", field.name, field.format(payload));
+            }
+        }
+    }
+
+    description
+}
+
+/// A Wireshark dissector for the payloads, as captured by the sniffer.
+///
+/// The packets hold the channel in their first byte, followed by the payload, and use the
+/// USER0 link type.
+pub fn lua_dissector() -> String {
+    let kinds = [
+        PayloadKind::ControllerState,
+        PayloadKind::Control,
+        PayloadKind::Telemetry,
+    ];
+    let mut lua = String::new();
+
+    let _ = writeln!(
+        lua,
+        "-- Wireshark dissector for the Robotoy radio payloads."
+    );
+    let _ = writeln!(
+        lua,
+        "-- Generated from src/protocol.rs by `rpi-controller dissector`, don't edit it."
+    );
+    let _ = writeln!(lua);
+    let _ = writeln!(
+        lua,
+        "local robotoy = Proto(\"robotoy\", \"Robotoy radio payload\")"
+    );
+    let _ = writeln!(lua);
+    let _ = writeln!(
+        lua,
+        "local channel = ProtoField.uint8(\"robotoy.channel\", \"Channel\", base.DEC)"
+    );
+    let mut names = vec![String::from("channel")];
+
+    for kind in kinds {
+        for field in kind.fields() {
+            let variable = format!("{}_{}", kind.name(), field.name);
+            let filter = format!("robotoy.{}.{}", kind.name(), field.name);
+            let constructor = match field.field_type {
+                FieldType::Flags(_) => "uint8(\"{filter}\", \"{name}\", base.HEX)",
+                FieldType::U8 => "uint8(\"{filter}\", \"{name}\", base.DEC)",
+                FieldType::U16 => "uint16(\"{filter}\", \"{name}\", base.DEC)",
+                FieldType::I16 => "int16(\"{filter}\", \"{name}\", base.DEC)",
+            }
+            .replace("{filter}", &filter)
+            .replace("{name}", field.name);
+            let _ = writeln!(lua, "local {} = ProtoField.{}", variable, constructor);
+            names.push(variable.clone());
+
+            if let FieldType::Flags(flags) = field.field_type {
+                for (bit, flag) in flags.iter().enumerate() {
+                    let _ = writeln!(
+                        lua,
+                        "local {variable}_{flag} = ProtoField.bool(\"{filter}.{flag}\", \"{flag}\", 8, nil, 0x{mask:02x})",
+                        mask = 1 << bit
+                    );
+                    names.push(format!("{}_{}", variable, flag));
+                }
+            }
+        }
+    }
+
+    let _ = writeln!(lua);
+    let _ = writeln!(lua, "robotoy.fields = {{ {} }}", names.join(", "));
+    let _ = writeln!(lua);
+    let _ = writeln!(lua, "function robotoy.dissector(buffer, pinfo, tree)");
+    let _ = writeln!(lua, "    pinfo.cols.protocol = \"Robotoy\"");
+    let _ = writeln!(lua, "    local root = tree:add(robotoy, buffer())");
+    let _ = writeln!(lua, "    root:add(channel, buffer(0, 1))");
+    let _ = writeln!(lua, "    local payload = buffer(1):tvb()");
+    let _ = writeln!(lua, "    local length = payload:len()");
+    let _ = writeln!(lua, "    local kind");
+    let _ = writeln!(
+        lua,
+        "    if length == {} and payload(1, 2):uint() == 0x{:02X}{:02X} then",
+        PAYLOAD_SIZE, CONTROL_MARKER[0], CONTROL_MARKER[1]
+    );
+    let _ = writeln!(lua, "        kind = \"control\"");
+    let _ = writeln!(lua, "    elseif length == {} then", PAYLOAD_SIZE);
+    let _ = writeln!(lua, "        kind = \"state\"");
+    let _ = writeln!(lua, "    elseif length == {} then", TELEMETRY_SIZE);
+    let _ = writeln!(lua, "        kind = \"telemetry\"");
+    let _ = writeln!(lua, "    else");
+    let _ = writeln!(lua, "        pinfo.cols.info = \"unknown\"");
+    let _ = writeln!(lua, "        return");
+    let _ = writeln!(lua, "    end");
+    let _ = writeln!(lua, "    pinfo.cols.info = kind");
+
+    for kind in kinds {
+        let _ = writeln!(lua, "    if kind == \"{}\" then", kind.name());
+        for field in kind.fields() {
+            let variable = format!("{}_{}", kind.name(), field.name);
+            let range = format!("payload({}, {})", field.offset, field.field_type.size());
+            if let FieldType::Flags(flags) = field.field_type {
+                let _ = writeln!(
+                    lua,
+                    "        local flags = root:add({}, {})",
+                    variable, range
+                );
+                for flag in flags {
+                    let _ = writeln!(lua, "        flags:add({}_{}, {})", variable, flag, range);
+                }
+            } else {
+                let _ = writeln!(lua, "        root:add({}, {})", variable, range);
+            }
+        }
+        let _ = writeln!(lua, "    end");
+    }
+
+    let _ = writeln!(lua, "end");
+    let _ = writeln!(lua);
+    let _ = writeln!(
+        lua,
+        "DissectorTable.get(\"wtap_encap\"):add(wtap.USER0, robotoy)"
+    );
+
+    lua
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::switch_frame;
+    use crate::controller::ControllerState;
+
+    #[test]
+    fn describes_every_kind_of_payload() {
+        let mut state = ControllerState::new();
+        state.x = true;
+        state.start = true;
+        state.throttle = -20_000;
+        assert_eq!(
+            describe(&state.to_bytes()),
+            "state buttons=[x start] l2=0 r2=0 yaw=0 throttle=-20000 pitch=0 roll=0"
+        );
+        assert_eq!(describe(&switch_frame(40)), "control switch channel=40");
+        assert_eq!(
+            describe(&[1, 0x1F, 0x40, 0xFF, 0x38]),
+            "telemetry mode=1 voltage=8000 current=-200"
+        );
+        assert_eq!(describe(&[0xAB, 0x01]), "unknown ab 01");
+    }
+
+    #[test]
+    fn the_dissector_is_up_to_date() {
+        assert_eq!(
+            include_str!("../wireshark/robotoy.lua"),
+            lua_dissector(),
+            "run `cargo run -- dissector > wireshark/robotoy.lua`"
+        );
+    }
+}
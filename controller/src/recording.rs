@@ -0,0 +1,246 @@
+//! Session recordings of the radio traffic.
+//!
+//! A recording starts with the `MAGIC` bytes, followed by one record per frame:
+//!
+//! |Field|Size|Description|
+//! |---|:---:|:---|
+//! |Kind|1|`RecordKind` of the payload|
+//! |Delay|1 ... 10|Microseconds since the previous record, LEB128 encoded|
+//! |Length|1|Payload length|
+//! |Payload|Length|The frame, exactly as it went over the air|
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const MAGIC: &[u8; 8] = b"RTOYREC1";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[repr(u8)]
+pub enum RecordKind {
+    /// A controller state frame sent to the robot.
+    ControllerState = 0x00,
+    /// A telemetry frame received from the robot.
+    Telemetry = 0x01,
+}
+
+impl TryFrom<u8> for RecordKind {
+    type Error = io::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0x00 => Ok(RecordKind::ControllerState),
+            0x01 => Ok(RecordKind::Telemetry),
+            _ => Err(io::Error::new(
+                ErrorKind::InvalidData,
+                format!("unknown record kind {:#04x}", value),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    /// Time since the recording started.
+    pub at: Duration,
+    pub kind: RecordKind,
+    pub payload: Vec<u8>,
+}
+
+pub struct Recorder<W: Write> {
+    out: W,
+    started: Instant,
+    last: Duration,
+}
+
+impl Recorder<BufWriter<File>> {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Recorder::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> Recorder<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(MAGIC)?;
+
+        Ok(Recorder {
+            out,
+            started: Instant::now(),
+            last: Duration::ZERO,
+        })
+    }
+
+    /// Appends a record timestamped with the time elapsed since the recorder was created.
+    pub fn record(&mut self, kind: RecordKind, payload: &[u8]) -> io::Result<()> {
+        self.record_at(self.started.elapsed(), kind, payload)
+    }
+
+    pub fn record_at(&mut self, at: Duration, kind: RecordKind, payload: &[u8]) -> io::Result<()> {
+        let length = u8::try_from(payload.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "payload too long"))?;
+
+        let delay = at.saturating_sub(self.last).as_micros() as u64;
+        self.last = at.max(self.last);
+
+        self.out.write_all(&[kind as u8])?;
+        write_varint(&mut self.out, delay)?;
+        self.out.write_all(&[length])?;
+        self.out.write_all(payload)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Reads the records of a recording back, in the order they were written.
+pub struct Player<R: Read> {
+    input: R,
+    at: Duration,
+}
+
+impl Player<BufReader<File>> {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        Player::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Player<R> {
+    pub fn new(mut input: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not a recording"));
+        }
+
+        Ok(Player {
+            input,
+            at: Duration::ZERO,
+        })
+    }
+
+    fn read_record(&mut self, kind: u8) -> io::Result<Record> {
+        let kind = RecordKind::try_from(kind)?;
+        let delay = read_varint(&mut self.input)?;
+
+        let mut length = [0u8; 1];
+        self.input.read_exact(&mut length)?;
+        let mut payload = vec![0u8; length[0] as usize];
+        self.input.read_exact(&mut payload)?;
+
+        self.at += Duration::from_micros(delay);
+
+        Ok(Record {
+            at: self.at,
+            kind,
+            payload,
+        })
+    }
+}
+
+impl<R: Read> Iterator for Player<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut kind = [0u8; 1];
+        match self.input.read_exact(&mut kind) {
+            Ok(()) => Some(self.read_record(kind[0])),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+fn write_varint(out: &mut impl Write, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            return out.write_all(&[byte]);
+        }
+        out.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint(input: &mut impl Read) -> io::Result<u64> {
+    let mut value: u64 = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8; 1];
+        input.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7F) as u64) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(io::Error::new(ErrorKind::InvalidData, "varint too long"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_back_what_was_recorded() {
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        let frame = [0x01, 0, 0, 0x7F, 0xFF, 0, 0, 0xB1, 0xE0, 0, 0, 0, 0];
+        recorder
+            .record_at(
+                Duration::from_micros(150),
+                RecordKind::ControllerState,
+                &frame,
+            )
+            .unwrap();
+        recorder
+            .record_at(Duration::from_secs(3), RecordKind::Telemetry, &[1, 2, 3])
+            .unwrap();
+        recorder
+            .record_at(Duration::from_secs(3), RecordKind::ControllerState, &[])
+            .unwrap();
+
+        let records: Vec<Record> = Player::new(&recorder.out[..])
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    at: Duration::from_micros(150),
+                    kind: RecordKind::ControllerState,
+                    payload: frame.to_vec(),
+                },
+                Record {
+                    at: Duration::from_secs(3),
+                    kind: RecordKind::Telemetry,
+                    payload: vec![1, 2, 3],
+                },
+                Record {
+                    at: Duration::from_secs(3),
+                    kind: RecordKind::ControllerState,
+                    payload: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_recordings() {
+        let error = Player::new(&b"RTOYREC0"[..]).err().unwrap();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reports_truncated_records() {
+        let mut recorder = Recorder::new(Vec::new()).unwrap();
+        recorder
+            .record_at(Duration::ZERO, RecordKind::ControllerState, &[1, 2, 3])
+            .unwrap();
+        let truncated = &recorder.out[..recorder.out.len() - 1];
+
+        let mut player = Player::new(truncated).unwrap();
+        let error = player.next().unwrap().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    }
+}
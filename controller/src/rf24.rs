@@ -1,11 +1,58 @@
-extern crate rppal;
-
-use rppal::{gpio, spi, spi::Spi};
+#[cfg(feature = "rppal")]
+mod builder;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock;
+mod snapshot;
+#[cfg(feature = "rppal")]
+mod spi_bus;
+
+#[cfg(feature = "rppal")]
+pub use builder::RadioBuilder;
+pub use snapshot::{Crc, RegisterSnapshot, Retransmit};
+#[cfg(feature = "rppal")]
+pub use spi_bus::{SpiBus, SpiConfig};
+
+use serde::Serialize;
+use std::error::Error;
 use std::fmt;
+use std::str::FromStr;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+// Auto retransmit used when ACK payloads are enabled: 500µs delay and 3 retries.
+// 500µs is the minimum delay for a short ACK payload at 250Kbps.
+const DEFAULT_RETRANSMIT: Retransmit = Retransmit {
+    delay_us: 500,
+    count: 3,
+};
+
+// Time for the crystal to start after power down (Tpd2stby), 1.5ms with the usual crystals.
+const POWER_UP_DELAY: Duration = Duration::from_micros(1500);
+
+// Time from CE high to sending or listening (Tstby2a).
+const SETTLE_DELAY: Duration = Duration::from_micros(130);
+
+// Interrupts are polled, so they are all masked.
+const MASK_IRQ: u8 = MASK_MAX_RT | MASK_TX_DS | MASK_RX_DR;
+
+// Largest payload the module can hold.
+const MAX_PAYLOAD_SIZE: u8 = 32;
 
-const SPI_SPEED: u32 = 10_000_000;
+/// How long to wait on the module unless told otherwise, see `Timeouts`.
+pub const DEFAULT_TIMEOUTS: Timeouts = Timeouts {
+    tx_fifo: Duration::from_millis(20),
+    // 15 retransmissions 4000µs apart, plus the air time
+    transmission: Duration::from_millis(100),
+};
+
+/// Time between two reads of the module while waiting on it.
+const POLL_INTERVAL: Duration = Duration::from_micros(100);
+
+/// Size of the controller state frames, the static payload size.
+pub const PAYLOAD_SIZE: u8 = 13;
+
+/// Payloads the TX FIFO holds.
+pub const TX_FIFO_SIZE: usize = 3;
 
 // CONFIG register bits
 const MASK_RX_DR: u8 = 0x40;
@@ -16,22 +63,9 @@ const CRCO: u8 = 0x04;
 const PWR_UP: u8 = 0x02;
 const PRIM_RX: u8 = 0x01;
 
-// EN_AA register bits
-const ENAA_P5: u8 = 0x20;
-const ENAA_P4: u8 = 0x10;
-const ENAA_P3: u8 = 0x08;
-const ENAA_P2: u8 = 0x04;
-const ENAA_P1: u8 = 0x02;
+// EN_AA register bits, only pipe 0 is used
 const ENAA_P0: u8 = 0x01;
 
-// EN_RXADDR register bits
-const ERX_P5: u8 = 0x20;
-const ERX_P4: u8 = 0x10;
-const ERX_P3: u8 = 0x08;
-const ERX_P2: u8 = 0x04;
-const ERX_P1: u8 = 0x02;
-const ERX_P0: u8 = 0x01;
-
 // SETUP_AW register bits
 const AW: u8 = 0x03;
 
@@ -78,12 +112,7 @@ const TX_EMPTY: u8 = 0x10;
 const RX_FULL: u8 = 0x02;
 const RX_EMPTY: u8 = 0x01;
 
-// DYNPD register bits
-const DPL_P5: u8 = 0x20;
-const DPL_P4: u8 = 0x10;
-const DPL_P3: u8 = 0x08;
-const DPL_P2: u8 = 0x04;
-const DPL_P1: u8 = 0x02;
+// DYNPD register bits, only pipe 0 is used
 const DPL_P0: u8 = 0x01;
 
 // FEATURE register bits
@@ -91,11 +120,14 @@ const EN_DPL: u8 = 0x04;
 const EN_ACK_PAY: u8 = 0x02;
 const EN_DYN_ACK: u8 = 0x01;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
 #[repr(u8)]
 pub enum DataRate {
+    #[serde(rename = "250Kbps")]
     _250Kbps = 0x20,
+    #[serde(rename = "1Mbps")]
     _1Mbps = 0x00,
+    #[serde(rename = "2Mbps")]
     _2Mbps = 0x08,
 }
 
@@ -109,12 +141,16 @@ impl fmt::Display for DataRate {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
 #[repr(u8)]
 pub enum PowerLevel {
+    #[serde(rename = "-18dBm")]
     _18dBm = 0x00,
+    #[serde(rename = "-12dBm")]
     _12dBm = 0x02,
+    #[serde(rename = "-6dBm")]
     _6dBm = 0x04,
+    #[serde(rename = "0dBm")]
     _0dBm = 0x06,
 }
 
@@ -129,6 +165,25 @@ impl fmt::Display for PowerLevel {
     }
 }
 
+impl FromStr for PowerLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "-18dBm" => Ok(PowerLevel::_18dBm),
+            "-12dBm" => Ok(PowerLevel::_12dBm),
+            "-6dBm" => Ok(PowerLevel::_6dBm),
+            "0dBm" => Ok(PowerLevel::_0dBm),
+            _ => Err(format!(
+                "unknown power level {}, expected -18dBm, -12dBm, -6dBm or 0dBm",
+                s
+            )),
+        }
+    }
+}
+
+/// The SPI commands, named as in the datasheet.
+#[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone)]
 #[repr(u8)]
 pub enum Command {
@@ -151,6 +206,8 @@ impl fmt::Display for Command {
     }
 }
 
+/// The registers, named as in the datasheet.
+#[allow(non_camel_case_types)]
 #[derive(Debug, Copy, Clone)]
 #[repr(u8)]
 pub enum Register {
@@ -188,48 +245,156 @@ impl fmt::Display for Register {
     }
 }
 
+/// The STATUS register, decoded.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize)]
+pub struct Status {
+    pub rx_dr: bool,
+    pub tx_ds: bool,
+    pub max_rt: bool,
+    /// Pipe of the payload on top of the RX FIFO, 7 when it's empty.
+    pub rx_p_no: u8,
+    pub tx_full: bool,
+}
+
+impl From<u8> for Status {
+    fn from(register: u8) -> Self {
+        Status {
+            rx_dr: register & RX_DR != 0,
+            tx_ds: register & TX_DS != 0,
+            max_rt: register & MAX_RT != 0,
+            rx_p_no: (register & RX_P_NO) >> 1,
+            tx_full: register & TX_FULL != 0,
+        }
+    }
+}
+
+/// The FIFO_STATUS register, decoded.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Serialize)]
+pub struct FifoStatus {
+    pub tx_reuse: bool,
+    pub tx_full: bool,
+    pub tx_empty: bool,
+    pub rx_full: bool,
+    pub rx_empty: bool,
+}
+
+impl From<u8> for FifoStatus {
+    fn from(register: u8) -> Self {
+        FifoStatus {
+            tx_reuse: register & TX_REUSE != 0,
+            tx_full: register & FIFO_FULL != 0,
+            tx_empty: register & TX_EMPTY != 0,
+            rx_full: register & RX_FULL != 0,
+            rx_empty: register & RX_EMPTY != 0,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum RF24Error {
-    GpioError(gpio::Error),
-    SpiError(spi::Error),
+    /// The CE or chip select pin can't be driven. The error comes from the bus.
+    GpioError(Box<dyn Error + Send + Sync>),
+    /// A transfer on the SPI bus failed. The error comes from the bus.
+    SpiError(Box<dyn Error + Send + Sync>),
     InvalidChannel,
     InvalidAddressWidth,
+    /// The delay isn't a multiple of 250µs from 250µs to 4000µs, or the count is over 15.
+    InvalidRetransmit,
+    /// The module didn't get done in time, what was waited for is in the message.
+    ///
+    /// The TX FIFO is flushed, so the payloads stuck in it don't go out later.
+    Timeout(&'static str),
+    /// `send_burst` was given more payloads than the TX FIFO holds.
+    BurstTooLong,
+    /// The settings can't be used together, the reason is in the message.
+    IncompatibleSettings(&'static str),
+    /// The module doesn't answer on the SPI bus, it's probably missing or miswired.
+    ChipNotConnected,
+    /// A register read back after `configure` doesn't hold the value that was written to it.
+    RegisterMismatch {
+        register: Register,
+        written: u8,
+        read: u8,
+    },
 }
 
-#[derive(Debug)]
-pub struct RF24 {
-    spi: Spi,
-    ce_pin: gpio::OutputPin,
+impl RF24Error {
+    /// Whether the operation may work if tried again.
+    ///
+    /// SPI transfers fail now and then on noisy wiring, and a register that doesn't hold its
+    /// value is put back by `reinitialize`. The other errors come from the wiring or the settings.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            RF24Error::SpiError(_) | RF24Error::Timeout(_) | RF24Error::RegisterMismatch { .. }
+        )
+    }
 }
 
-impl RF24 {
-    pub fn new(ce_pin_number: u8) -> Result<RF24, RF24Error> {
-        let ce_pin = gpio::Gpio::new()
-            .map_err(|e| RF24Error::GpioError(e))?
-            .get(ce_pin_number)
-            .map_err(|e| RF24Error::GpioError(e))?
-            .into_output_low();
-
-        let spi: Spi = Spi::new(
-            spi::Bus::Spi0,
-            spi::SlaveSelect::Ss0,
-            SPI_SPEED,
-            spi::Mode::Mode0,
-        )
-        .map_err(|e| RF24Error::SpiError(e))?;
+impl fmt::Display for RF24Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RF24Error::GpioError(e) => write!(f, "GPIO error: {}", e),
+            RF24Error::SpiError(e) => write!(f, "SPI error: {}", e),
+            RF24Error::InvalidChannel => write!(f, "the channel must be from 0 to 127"),
+            RF24Error::InvalidAddressWidth => write!(f, "the address must be 3 to 5 bytes long"),
+            RF24Error::InvalidRetransmit => write!(
+                f,
+                "the retransmit delay must be a multiple of 250µs up to 4000µs, and the count at most 15"
+            ),
+            RF24Error::Timeout(waiting_for) => write!(f, "timed out waiting for {}", waiting_for),
+            RF24Error::BurstTooLong => {
+                write!(f, "a burst holds at most {} payloads", TX_FIFO_SIZE)
+            }
+            RF24Error::IncompatibleSettings(reason) => write!(f, "incompatible settings: {}", reason),
+            RF24Error::ChipNotConnected => write!(f, "the radio module doesn't answer"),
+            RF24Error::RegisterMismatch {
+                register,
+                written,
+                read,
+            } => write!(
+                f,
+                "{:?} holds {:#04x} instead of {:#04x}",
+                register, read, written
+            ),
+        }
+    }
+}
+
+impl Error for RF24Error {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RF24Error::GpioError(e) => Some(e.as_ref()),
+            RF24Error::SpiError(e) => Some(e.as_ref()),
+            _ => None,
+        }
+    }
+}
 
-        sleep(Duration::from_millis(5));
+/// The wires used to talk to the NRF24L01+ module: the SPI bus and the CE pin.
+pub trait Bus: Send {
+    /// Clocks `write` out while reading the same amount of bytes into `read`.
+    ///
+    /// It returns the number of bytes transferred.
+    fn transfer(&self, read: &mut [u8], write: &[u8]) -> Result<usize, RF24Error>;
+
+    fn set_ce(&mut self, high: bool);
+}
 
-        Ok(RF24 { spi, ce_pin })
+pub struct RF24 {
+    bus: Box<dyn Bus>,
+}
+
+impl RF24 {
+    pub fn with_bus(bus: Box<dyn Bus>) -> RF24 {
+        RF24 { bus }
     }
 
     /// Send a command to the NRF24L01+ module and reads the response.
     ///
     /// It returns the number of bytes read.
     fn command(&self, data_out: &[u8], data_in: &mut [u8]) -> Result<usize, RF24Error> {
-        self.spi
-            .transfer(data_in, data_out)
-            .map_err(|e| RF24Error::SpiError(e))
+        self.bus.transfer(data_in, data_out)
     }
 
     /// Reads a register from the NRF24L01+ module.
@@ -297,119 +462,185 @@ impl RF24 {
         Ok(())
     }
 
-    /// Enable CE pin to start transmission.
-    fn set_ce_high(&mut self) {
-        self.ce_pin.set_high();
+    /// Writes a payload to the TX FIFO that the receiver won't acknowledge.
+    ///
+    /// Needs `EN_DYN_ACK` in FEATURE, the module ignores the command otherwise.
+    fn write_payload_no_ack(&self, data: &[u8]) -> Result<(), RF24Error> {
+        let mut data_out: Vec<u8> = vec![0; data.len() + 1];
+        let mut data_in: Vec<u8> = vec![0; data.len() + 1];
+        data_out[0] = Command::W_TX_PAYLOAD_NOACK as u8;
+        data_out[1..].copy_from_slice(data);
+        self.command(&data_out, &mut data_in)?;
+        Ok(())
     }
 
-    /// Disable CE pin to stop transmission.
-    fn set_ce_low(&mut self) {
-        self.ce_pin.set_low();
+    /// Keeps the last payload in the TX FIFO, to send it again on every CE pulse.
+    ///
+    /// It lasts until the next payload is written or the TX FIFO is flushed.
+    fn reuse_tx_payload(&self) -> Result<(), RF24Error> {
+        let mut data_in = [0u8; 1];
+        self.command(&[Command::REUSE_TX_PL as u8], &mut data_in)?;
+        Ok(())
     }
 
-    fn print_details(&self) -> Result<(), RF24Error> {
-        let mut register = self.read_register(Register::STATUS)?;
-        print!("STATUS\t\t= {:#04x}", register);
-        print!(" RX_DR={}", (register & RX_DR) >> 6);
-        print!(" TX_DS={}", (register & TX_DS) >> 5);
-        print!(" MAX_RT={}", (register & MAX_RT) >> 4);
-        print!(" RX_P_NO={}", (register & RX_P_NO) >> 1);
-        println!(" TX_FULL={}", register & TX_FULL);
-
-        let mut full_address = [0u8; 5];
-        self.read_address(Register::RX_ADDR_P0, 5, &mut full_address)?;
-        print!("RX_ADDR_P0-1    = 0x");
-        for i in full_address {
-            print!("{:02x}", i);
-        }
-
-        self.read_address(Register::RX_ADDR_P1, 5, &mut full_address)?;
-        print!(" 0x");
-        for i in full_address {
-            print!("{:02x}", i);
-        }
-
-        let mut short_address: u8 = 0;
-        print!("\nRX_ADDR_P2-5   = ");
-        short_address = self.read_register(Register::RX_ADDR_P2)?;
-        print!(" 0x{:02x}", short_address);
-        short_address = self.read_register(Register::RX_ADDR_P3)?;
-        print!(" 0x{:02x}", short_address);
-        short_address = self.read_register(Register::RX_ADDR_P4)?;
-        print!(" 0x{:02x}", short_address);
-        short_address = self.read_register(Register::RX_ADDR_P5)?;
-        println!(" 0x{:02x}", short_address);
-
-        self.read_address(Register::TX_ADDR, 5, &mut full_address)?;
-        print!("TX_ADDR         = 0x");
-        for i in full_address {
-            print!("{:02x}", i);
-        }
-        println!("");
+    /// Reads the width of the payload on top of the RX FIFO, when dynamic payloads are enabled.
+    fn read_payload_width(&self) -> Result<u8, RF24Error> {
+        let mut data_in = [0u8; 2];
+        self.command(&[Command::R_RX_PL_WID as u8, 0], &mut data_in)?;
+        Ok(data_in[1])
+    }
 
-        register = self.read_register(Register::EN_AA)?;
-        println!("EN_AA           = {:#04x}", register);
+    /// Reads a payload of `size` bytes from the RX FIFO.
+    fn read_payload(&self, size: usize) -> Result<Vec<u8>, RF24Error> {
+        let mut data_out: Vec<u8> = vec![0; size + 1];
+        let mut data_in: Vec<u8> = vec![0; size + 1];
+        data_out[0] = Command::R_RX_PAYLOAD as u8;
+        self.command(&data_out, &mut data_in)?;
+        Ok(data_in[1..].to_vec())
+    }
 
-        register = self.read_register(Register::EN_RXADDR)?;
-        println!("EN_RXADDR       = {:#04x}", register);
+    /// Enable CE pin to start transmission.
+    fn set_ce_high(&mut self) {
+        self.bus.set_ce(true);
+    }
 
-        register = self.read_register(Register::RF_CH)?;
-        println!("RF_CH           = {:#04x}", register);
+    /// Disable CE pin to stop transmission.
+    fn set_ce_low(&mut self) {
+        self.bus.set_ce(false);
+    }
+}
 
-        register = self.read_register(Register::RF_SETUP)?;
-        println!("RF_SETUP        = {:#04x}", register);
+/// The operating modes of the module, from the state diagram of the datasheet.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum PowerState {
+    /// Everything off but the SPI and the registers, about 1µA.
+    PowerDown,
+    /// Crystal running, ready to send or listen within 130µs, about 26µA.
+    StandbyI,
+    /// Transmitting whatever is in the TX FIFO, or Standby-II when it's empty.
+    Tx,
+    Rx,
+}
 
-        register = self.read_register(Register::CONFIG)?;
-        println!("CONFIG          = {:#04x}", register);
+/// How long `Radio` waits on the module before giving up with `RF24Error::Timeout`.
+///
+/// A module that's unplugged, or whose CE is stuck, never gets done sending.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Timeouts {
+    /// For room in the TX FIFO.
+    pub tx_fifo: Duration,
+    /// For a payload, or a whole burst, to be sent and acknowledged, retransmissions included.
+    pub transmission: Duration,
+}
 
-        register = self.read_register(Register::DYNPD)?;
-        let feature = self.read_register(Register::FEATURE)?;
-        println!("DYNPD/FEATURE   = {:#04x} {:#04x}", register, feature);
+/// A payload of a burst, see `Radio::send_burst`.
+#[derive(Debug, Copy, Clone)]
+pub struct TxPayload<'a> {
+    pub data: &'a [u8],
+    /// Asks the receiver not to acknowledge it, so the transmitter doesn't wait for the ACK.
+    pub no_ack: bool,
+}
 
-        Ok(())
-    }
+/// What is known about a payload after `Radio::send`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Delivery {
+    /// Auto-ack is disabled, so there is no way to know if the payload arrived.
+    Unacknowledged,
+    /// The receiver acknowledged the payload, maybe attaching a payload to the ACK.
+    Acked {
+        retries: u8,
+        payload: Option<Vec<u8>>,
+    },
+    /// No ACK arrived, even after all the retransmissions.
+    Lost,
 }
 
 pub struct Radio {
     rf24: RF24,
-    address: [u8; 5],
+    address: Vec<u8>,
     rate: DataRate,
     power_level: PowerLevel,
+    crc: Crc,
+    retransmit: Retransmit,
     channel: u8,
-    ce_pin_number: u8,
+    ack_payloads: bool,
+    passive: bool,
+    power_state: PowerState,
+    timeouts: Timeouts,
+    status: u8,
 }
 
 impl Radio {
-    pub fn new(
+    /// Starts describing a radio with the given address, wired to the Raspberry Pi SPI.
+    #[cfg(feature = "rppal")]
+    pub fn builder(address: &str) -> RadioBuilder {
+        RadioBuilder::new(address)
+    }
+
+    /// Creates a radio that talks to the module through the given bus instead of the Raspberry Pi SPI.
+    pub fn with_bus(
+        bus: Box<dyn Bus>,
         address: &str,
         rate: DataRate,
         power_level: PowerLevel,
         channel: u8,
-        ce_pin_number: u8,
     ) -> Result<Radio, RF24Error> {
-        let rf24 = RF24::new(ce_pin_number)?;
+        Radio::with_rf24(RF24::with_bus(bus), address, rate, power_level, channel)
+    }
 
+    fn with_rf24(
+        rf24: RF24,
+        address: &str,
+        rate: DataRate,
+        power_level: PowerLevel,
+        channel: u8,
+    ) -> Result<Radio, RF24Error> {
         let mut radio = Radio {
             rf24,
             rate,
             power_level,
-            address: [0; 5],
+            crc: Crc::TwoBytes,
+            retransmit: DEFAULT_RETRANSMIT,
+            address: Vec::new(),
             channel: 0,
-            ce_pin_number,
+            ack_payloads: false,
+            passive: false,
+            power_state: PowerState::PowerDown,
+            timeouts: DEFAULT_TIMEOUTS,
+            status: 0,
         };
 
+        if channel > 127 {
+            return Err(RF24Error::InvalidChannel);
+        }
+        check_settings(rate, radio.crc, radio.ack_payloads, radio.retransmit)?;
+        // Written by `configure`
+        radio.channel = channel;
         radio.set_address(address)?;
-        radio.set_channel(channel)?;
 
         Ok(radio)
     }
 
+    /// Sets the address the payloads are sent to, from 3 to 5 bytes.
+    ///
+    /// The address width of the module follows the length of the address.
     pub fn set_address(&mut self, address: &str) -> Result<(), RF24Error> {
         let address = address.as_bytes();
-        if address.len() != 5 {
+        if !(3..=5).contains(&address.len()) {
             return Err(RF24Error::InvalidAddressWidth);
         }
-        self.address.copy_from_slice(address);
+        self.address = address.to_vec();
+        self.write_address()
+    }
+
+    /// Width of the address in bytes.
+    pub fn address_width(&self) -> u8 {
+        self.address.len() as u8
+    }
+
+    fn write_address(&self) -> Result<(), RF24Error> {
+        self.rf24
+            .write_register(Register::SETUP_AW, self.setup_aw())?;
         self.rf24
             .write_full_address(Register::RX_ADDR_P0, &self.address)?;
         self.rf24
@@ -417,41 +648,232 @@ impl Radio {
         Ok(())
     }
 
+    fn setup_aw(&self) -> u8 {
+        (self.address.len() as u8 - 2) & AW
+    }
+
+    /// Changes the data rate right away, without configuring the radio again.
+    pub fn set_data_rate(&mut self, rate: DataRate) -> Result<(), RF24Error> {
+        check_settings(rate, self.crc, self.ack_payloads, self.retransmit)?;
+        self.rate = rate;
+        self.write_rf_setup()
+    }
+
+    /// Changes the power level right away, without configuring the radio again.
+    pub fn set_power_level(&mut self, power_level: PowerLevel) -> Result<(), RF24Error> {
+        self.power_level = power_level;
+        self.write_rf_setup()
+    }
+
+    fn write_rf_setup(&self) -> Result<(), RF24Error> {
+        let rf_setup = self.rate as u8 | self.power_level as u8;
+        self.rf24.write_register(Register::RF_SETUP, rf_setup)?;
+        self.verify_register(Register::RF_SETUP, rf_setup)
+    }
+
+    /// Changes the CRC length right away. Auto-ack doesn't work without CRC.
+    pub fn set_crc(&mut self, crc: Crc) -> Result<(), RF24Error> {
+        check_settings(self.rate, crc, self.ack_payloads, self.retransmit)?;
+        self.crc = crc;
+
+        // Keeps the power and RX/TX state the radio is in
+        let state = self.rf24.read_register(Register::CONFIG)? & (PWR_UP | PRIM_RX);
+        let config = self.config() | state;
+        self.rf24.write_register(Register::CONFIG, config)?;
+        self.verify_register(Register::CONFIG, config)
+    }
+
+    pub fn crc(&self) -> Crc {
+        self.crc
+    }
+
+    /// Sets how long to wait for an ACK before sending again, and how many times to try, when
+    /// ACK payloads are enabled.
+    ///
+    /// The delay goes from 250µs to 4000µs in steps of 250µs, and the count up to 15.
+    pub fn set_retransmit(&mut self, delay_us: u16, count: u8) -> Result<(), RF24Error> {
+        if !(250..=4000).contains(&delay_us) || !delay_us.is_multiple_of(250) || count > 15 {
+            return Err(RF24Error::InvalidRetransmit);
+        }
+        let retransmit = Retransmit { delay_us, count };
+        check_settings(self.rate, self.crc, self.ack_payloads, retransmit)?;
+        self.retransmit = retransmit;
+
+        let setup_retr = retransmit.register();
+        self.rf24.write_register(Register::SETUP_RETR, setup_retr)?;
+        self.verify_register(Register::SETUP_RETR, setup_retr)
+    }
+
+    pub fn retransmit(&self) -> Retransmit {
+        self.retransmit
+    }
+
+    /// CONFIG without PWR_UP and PRIM_RX.
+    fn config(&self) -> u8 {
+        MASK_IRQ | self.crc.config_bits()
+    }
+
+    /// Moves to another channel, and checks the module took it.
+    ///
+    /// Whatever is left in the TX FIFO was meant for the old channel, so it's dropped.
     pub fn set_channel(&mut self, channel: u8) -> Result<(), RF24Error> {
         if channel > 127 {
             return Err(RF24Error::InvalidChannel);
         }
         self.channel = channel;
         self.rf24.write_register(Register::RF_CH, channel)?;
+        self.rf24.flush_tx()?;
+        self.verify_register(Register::RF_CH, channel)
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    pub fn data_rate(&self) -> DataRate {
+        self.rate
+    }
+
+    pub fn power_level(&self) -> PowerLevel {
+        self.power_level
+    }
+
+    /// The STATUS register as it was last seen by `send`, without talking to the module.
+    pub fn status(&self) -> Status {
+        Status::from(self.status)
+    }
+
+    pub fn fifo_status(&self) -> Result<FifoStatus, RF24Error> {
+        let register = self.rf24.read_register(Register::FIFO_STATUS)?;
+        Ok(FifoStatus::from(register))
+    }
+
+    pub fn power_state(&self) -> PowerState {
+        self.power_state
+    }
+
+    /// Moves the module to another operating mode.
+    ///
+    /// CE always goes low while CONFIG changes, and the module is given the time the datasheet
+    /// asks for to start the crystal after power down and to settle before sending or listening.
+    pub fn set_power_state(&mut self, state: PowerState) -> Result<(), RF24Error> {
+        self.rf24.set_ce_low();
+        self.rf24
+            .write_register(Register::CONFIG, self.config() | state_bits(state))?;
+
+        if self.power_state == PowerState::PowerDown && state != PowerState::PowerDown {
+            sleep(POWER_UP_DELAY);
+        }
+        if matches!(state, PowerState::Tx | PowerState::Rx) {
+            self.rf24.set_ce_high();
+            sleep(SETTLE_DELAY);
+        }
+
+        self.power_state = state;
         Ok(())
     }
 
-    pub fn configure(&self) -> Result<(), RF24Error> {
-        // Disable interrupts, enable CRC, 2 bytes CRC, and set as primary receiver
-        let config: u8 = MASK_MAX_RT | MASK_TX_DS | MASK_RX_DR | EN_CRC | CRCO;
-        self.rf24.write_register(Register::CONFIG, config)?;
+    /// Powers up as primary receiver and starts listening on the current channel.
+    pub fn start_listening(&mut self) -> Result<(), RF24Error> {
+        self.set_power_state(PowerState::Rx)
+    }
+
+    /// Stops listening and goes back to standby, as primary transmitter.
+    pub fn stop_listening(&mut self) -> Result<(), RF24Error> {
+        self.set_power_state(PowerState::StandbyI)
+    }
+
+    /// Whether a signal over -64dBm was received on the current channel while listening.
+    ///
+    /// The module needs to listen for at least 170µs before the detector settles.
+    pub fn received_power_detected(&self) -> Result<bool, RF24Error> {
+        Ok(self.rf24.read_register(Register::RPD)? & RPD != 0)
+    }
+
+    /// Transmits a constant carrier on `channel`, until `stop_carrier_wave`.
+    ///
+    /// It's a test mode from the datasheet, to measure the output power and check the antenna
+    /// with a spectrum analyzer.
+    pub fn start_carrier_wave(
+        &mut self,
+        channel: u8,
+        power_level: PowerLevel,
+    ) -> Result<(), RF24Error> {
+        if channel > 127 {
+            return Err(RF24Error::InvalidChannel);
+        }
 
-        // Set channel
-        self.rf24.write_register(Register::RF_CH, self.channel)?;
+        self.set_power_state(PowerState::StandbyI)?;
 
-        // Set data rate and power level
-        let rf_setup: u8 = self.rate as u8 | self.power_level as u8;
+        let rf_setup = CONT_WAVE | PLL_LOCK | power_level as u8;
         self.rf24.write_register(Register::RF_SETUP, rf_setup)?;
+        self.rf24.write_register(Register::RF_CH, channel)?;
+        self.rf24.set_ce_high();
+        self.power_state = PowerState::Tx;
+
+        Ok(())
+    }
+
+    /// Whether the module accepted the carrier wave settings on `channel`: the continuous wave
+    /// and forced PLL lock bits, read back from RF_SETUP.
+    ///
+    /// The nRF24 can't report whether the synthesizer actually locked, only a spectrum analyzer
+    /// can tell.
+    pub fn forced_pll_lock_set(&self, channel: u8) -> Result<bool, RF24Error> {
+        let forced = CONT_WAVE | PLL_LOCK;
+        let rf_setup = self.rf24.read_register(Register::RF_SETUP)?;
+        let rf_ch = self.rf24.read_register(Register::RF_CH)?;
+        Ok(rf_setup & forced == forced && rf_ch & RF_CH == channel)
+    }
+
+    /// Stops the carrier and configures the radio again to send payloads.
+    pub fn stop_carrier_wave(&mut self) -> Result<(), RF24Error> {
+        self.rf24.set_ce_low();
+        self.configure()
+    }
+
+    /// Enables auto-ack with payloads, so the receiver can send data back with each ACK.
+    ///
+    /// It takes effect on the next `configure`.
+    pub fn set_ack_payloads(&mut self, enabled: bool) -> Result<(), RF24Error> {
+        check_settings(self.rate, self.crc, enabled, self.retransmit)?;
+        self.ack_payloads = enabled;
+        Ok(())
+    }
+
+    pub fn ack_payloads(&self) -> bool {
+        self.ack_payloads
+    }
+
+    /// Receives what is sent to the address without acknowledging it, ACKs of the actual
+    /// receiver included, for a module that listens next to the one sending.
+    ///
+    /// The ACK payload setting must match the transmitter, for the payloads to have the same
+    /// format. It takes effect on the next `configure`.
+    pub fn set_passive(&mut self, enabled: bool) {
+        self.passive = enabled;
+    }
+
+    /// Checks that a module answers on the SPI bus.
+    ///
+    /// The unused bits of SETUP_AW always read 0 and the address width can't be 0, so a floating
+    /// MISO line, reading all 0s or all 1s, doesn't pass.
+    pub fn is_chip_connected(&self) -> Result<bool, RF24Error> {
+        let setup_aw = self.rf24.read_register(Register::SETUP_AW)?;
+        Ok(setup_aw & !AW == 0 && setup_aw & AW != 0)
+    }
 
-        // Disable auto-ack
-        self.rf24.write_register(Register::EN_AA, 0)?;
+    pub fn configure(&mut self) -> Result<(), RF24Error> {
+        if !self.is_chip_connected()? {
+            return Err(RF24Error::ChipNotConnected);
+        }
 
-        // Disable features
-        self.rf24.write_register(Register::FEATURE, 0)?;
+        self.rf24.write_register(Register::CONFIG, self.config())?;
 
-        // Set payload size
-        let payload_size: u8 = 13;
-        self.rf24.write_register(Register::RX_PW_P0, payload_size)?;
-        self.rf24.write_register(Register::RX_PW_P1, payload_size)?;
-        self.rf24.write_register(Register::RX_PW_P2, payload_size)?;
-        self.rf24.write_register(Register::RX_PW_P3, payload_size)?;
-        self.rf24.write_register(Register::RX_PW_P4, payload_size)?;
-        self.rf24.write_register(Register::RX_PW_P5, payload_size)?;
+        let registers = self.registers();
+        for (register, value) in &registers {
+            self.rf24.write_register(*register, *value)?;
+        }
 
         // Flush RX and TX
         self.rf24.flush_rx()?;
@@ -463,23 +885,118 @@ impl Radio {
 
         // Power up
         self.rf24
-            .write_register(Register::CONFIG, config | PWR_UP)?;
+            .write_register(Register::CONFIG, self.config() | PWR_UP)?;
+        self.power_state = PowerState::StandbyI;
 
         sleep(Duration::from_millis(130));
 
+        self.verify_configuration()
+    }
+
+    /// Reads the configuration back and checks it's still the one `configure` wrote.
+    ///
+    /// A module that browned out is back to its reset values, and powered down.
+    pub fn verify_configuration(&self) -> Result<(), RF24Error> {
+        let config = self.config() | state_bits(self.power_state);
+        self.verify_register(Register::CONFIG, config)?;
+        for (register, value) in self.registers() {
+            self.verify_register(register, value)?;
+        }
+        self.verify_address(Register::RX_ADDR_P0)?;
+        self.verify_address(Register::TX_ADDR)?;
+
+        Ok(())
+    }
+
+    /// Writes the whole configuration again, including the address and the channel.
+    pub fn reinitialize(&mut self) -> Result<(), RF24Error> {
+        self.rf24.set_ce_low();
+        self.write_address()?;
+        self.configure()
+    }
+
+    /// The values `configure` writes, besides CONFIG and the addresses.
+    fn registers(&self) -> Vec<(Register, u8)> {
+        let mut registers = vec![
+            (Register::SETUP_AW, self.setup_aw()),
+            (Register::RF_CH, self.channel),
+            // Data rate and power level
+            (Register::RF_SETUP, self.rate as u8 | self.power_level as u8),
+            (Register::SETUP_RETR, self.retransmit.register()),
+        ];
+
+        if self.ack_payloads {
+            // Auto-ack on pipe 0, which receives the ACKs, with dynamic payloads so they can carry data
+            let auto_ack = if self.passive { 0 } else { ENAA_P0 };
+            registers.extend([
+                (Register::EN_AA, auto_ack),
+                (Register::DYNPD, DPL_P0),
+                (Register::FEATURE, EN_DPL | EN_ACK_PAY | EN_DYN_ACK),
+            ]);
+        } else {
+            // Disable auto-ack and features, but the no-ACK payloads of bursts
+            registers.extend([
+                (Register::EN_AA, 0),
+                (Register::DYNPD, 0),
+                (Register::FEATURE, EN_DYN_ACK),
+            ]);
+        }
+
+        // Payload size
+        registers.extend([
+            (Register::RX_PW_P0, PAYLOAD_SIZE),
+            (Register::RX_PW_P1, PAYLOAD_SIZE),
+            (Register::RX_PW_P2, PAYLOAD_SIZE),
+            (Register::RX_PW_P3, PAYLOAD_SIZE),
+            (Register::RX_PW_P4, PAYLOAD_SIZE),
+            (Register::RX_PW_P5, PAYLOAD_SIZE),
+        ]);
+
+        registers
+    }
+
+    fn verify_register(&self, register: Register, written: u8) -> Result<(), RF24Error> {
+        let read = self.rf24.read_register(register)?;
+        if read != written {
+            return Err(RF24Error::RegisterMismatch {
+                register,
+                written,
+                read,
+            });
+        }
         Ok(())
     }
 
-    // Send a payload with no ack
-    pub fn send(&mut self, payload: &[u8]) -> Result<(), RF24Error> {
-        // Wait for TX FIFO to be empty
-        'fifo_full: loop {
-            let status = self.rf24.read_register(Register::STATUS)?;
-            if status & TX_FULL == 0 {
-                break 'fifo_full;
+    fn verify_address(&self, register: Register) -> Result<(), RF24Error> {
+        let mut address = vec![0u8; self.address.len()];
+        self.rf24
+            .read_address(register, self.address.len(), &mut address)?;
+        for (written, read) in self.address.iter().zip(address) {
+            if *written != read {
+                return Err(RF24Error::RegisterMismatch {
+                    register,
+                    written: *written,
+                    read,
+                });
             }
-            sleep(Duration::from_micros(100));
         }
+        Ok(())
+    }
+
+    /// Sends a payload.
+    ///
+    /// Without ACK payloads it doesn't wait for the transmission to finish, otherwise it waits
+    /// for the ACK and returns the payload attached to it.
+    pub fn send(&mut self, payload: &[u8]) -> Result<Delivery, RF24Error> {
+        if self.power_state != PowerState::StandbyI {
+            self.set_power_state(PowerState::StandbyI)?;
+        }
+
+        // Wait for room in the TX FIFO
+        self.wait(self.timeouts.tx_fifo, "room in the TX FIFO", |radio| {
+            let status = radio.read_status()?;
+            Ok((status & TX_FULL == 0).then_some(()))
+        })?;
 
         // Write payload
         self.rf24.write_payload(payload)?;
@@ -487,14 +1004,605 @@ impl Radio {
         sleep(Duration::from_micros(15));
         self.rf24.set_ce_low();
 
+        let delivery = if self.ack_payloads {
+            self.wait_for_ack()?
+        } else {
+            Delivery::Unacknowledged
+        };
+
         // Clear Status
         let status: u8 = RX_DR | TX_DS | MAX_RT;
         self.rf24.write_register(Register::STATUS, status)?;
 
-        Ok(())
+        Ok(delivery)
+    }
+
+    fn wait_for_ack(&mut self) -> Result<Delivery, RF24Error> {
+        let status = self.wait_for_transmission()?;
+
+        if status & MAX_RT != 0 {
+            // The payload stays in the TX FIFO after MAX_RT, drop it so the next one can go.
+            self.rf24.flush_tx()?;
+            return Ok(Delivery::Lost);
+        }
+
+        let retries = self.rf24.read_register(Register::OBSERVE_TX)? & ARC_CNT;
+
+        let mut payload = None;
+        if status & RX_DR != 0 {
+            payload = self.read_dynamic_payload()?;
+        }
+
+        Ok(Delivery::Acked { retries, payload })
+    }
+
+    /// Waits for the payload on top of the TX FIFO to be sent, or to run out of retransmissions.
+    fn wait_for_transmission(&mut self) -> Result<u8, RF24Error> {
+        self.wait(self.timeouts.transmission, "the transmission", |radio| {
+            let status = radio.read_status()?;
+            Ok((status & (TX_DS | MAX_RT) != 0).then_some(status))
+        })
     }
 
-    pub fn print_rf_details(&self) {
-        self.rf24.print_details().unwrap();
+    fn read_status(&mut self) -> Result<u8, RF24Error> {
+        self.status = self.rf24.read_register(Register::STATUS)?;
+        Ok(self.status)
+    }
+
+    /// Polls the module until `done` returns a value, for at most `timeout`.
+    ///
+    /// On timeout the transmission is stopped and the TX FIFO flushed.
+    fn wait<T>(
+        &mut self,
+        timeout: Duration,
+        waiting_for: &'static str,
+        mut done: impl FnMut(&mut Radio) -> Result<Option<T>, RF24Error>,
+    ) -> Result<T, RF24Error> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(value) = done(self)? {
+                return Ok(value);
+            }
+            if Instant::now() >= deadline {
+                if self.power_state == PowerState::Tx {
+                    self.rf24.set_ce_low();
+                    self.power_state = PowerState::StandbyI;
+                }
+                self.rf24.flush_tx()?;
+                return Err(RF24Error::Timeout(waiting_for));
+            }
+            sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Reads the payload on top of the RX FIFO, if its width makes sense.
+    fn read_dynamic_payload(&mut self) -> Result<Option<Vec<u8>>, RF24Error> {
+        let width = self.rf24.read_payload_width()?;
+        if width > MAX_PAYLOAD_SIZE {
+            // Corrupted width, the datasheet says to flush the RX FIFO
+            self.rf24.flush_rx()?;
+            return Ok(None);
+        }
+        Ok(Some(self.rf24.read_payload(width as usize)?))
+    }
+
+    /// Takes the next payload out of the RX FIFO, while listening.
+    pub fn receive(&mut self) -> Result<Option<Vec<u8>>, RF24Error> {
+        if self.rf24.read_register(Register::FIFO_STATUS)? & RX_EMPTY != 0 {
+            return Ok(None);
+        }
+
+        let payload = if self.ack_payloads {
+            self.read_dynamic_payload()?
+        } else {
+            Some(self.rf24.read_payload(PAYLOAD_SIZE as usize)?)
+        };
+        self.rf24.write_register(Register::STATUS, RX_DR)?;
+
+        Ok(payload)
+    }
+
+    /// Queues up to `TX_FIFO_SIZE` payloads and sends them back to back, keeping CE high.
+    ///
+    /// Payloads marked `no_ack` go out without waiting for an ACK, even with ACK payloads
+    /// enabled. The delivery covers the whole burst: it's `Lost` as soon as a payload gets no
+    /// ACK, the ones after it being dropped, and carries the last ACK payload otherwise.
+    pub fn send_burst(&mut self, payloads: &[TxPayload]) -> Result<Delivery, RF24Error> {
+        if payloads.len() > TX_FIFO_SIZE {
+            return Err(RF24Error::BurstTooLong);
+        }
+        if self.power_state != PowerState::StandbyI {
+            self.set_power_state(PowerState::StandbyI)?;
+        }
+
+        // Wait for the previous payloads to leave the TX FIFO
+        self.wait(self.timeouts.tx_fifo, "an empty TX FIFO", |radio| {
+            let fifo_status = radio.rf24.read_register(Register::FIFO_STATUS)?;
+            Ok((fifo_status & TX_EMPTY != 0).then_some(()))
+        })?;
+
+        for payload in payloads {
+            if payload.no_ack {
+                self.rf24.write_payload_no_ack(payload.data)?;
+            } else {
+                self.rf24.write_payload(payload.data)?;
+            }
+        }
+
+        // Transmits until the TX FIFO is empty, or a payload isn't acknowledged
+        self.set_power_state(PowerState::Tx)?;
+        let status = self.wait(self.timeouts.transmission, "the burst", |radio| {
+            let status = radio.read_status()?;
+            let tx_empty = radio.rf24.read_register(Register::FIFO_STATUS)? & TX_EMPTY != 0;
+            Ok((status & MAX_RT != 0 || tx_empty).then_some(status))
+        })?;
+        self.set_power_state(PowerState::StandbyI)?;
+
+        let acked = payloads.iter().any(|payload| !payload.no_ack);
+        let delivery = if status & MAX_RT != 0 {
+            self.rf24.flush_tx()?;
+            Delivery::Lost
+        } else if self.ack_payloads && acked {
+            let retries = self.rf24.read_register(Register::OBSERVE_TX)? & ARC_CNT;
+            let mut payload = None;
+            while self.rf24.read_register(Register::FIFO_STATUS)? & RX_EMPTY == 0 {
+                payload = self.read_dynamic_payload()?.or(payload);
+            }
+            Delivery::Acked { retries, payload }
+        } else {
+            Delivery::Unacknowledged
+        };
+
+        // Clear Status
+        let status: u8 = RX_DR | TX_DS | MAX_RT;
+        self.rf24.write_register(Register::STATUS, status)?;
+
+        Ok(delivery)
+    }
+
+    /// Sends `copies` copies of a payload, writing it to the module only once.
+    ///
+    /// Meant for critical frames: with ACK payloads the delivery is the one of the last copy
+    /// that was acknowledged, and `Lost` if none was.
+    pub fn send_copies(&mut self, payload: &[u8], copies: usize) -> Result<Delivery, RF24Error> {
+        if self.power_state != PowerState::StandbyI {
+            self.set_power_state(PowerState::StandbyI)?;
+        }
+
+        // Start from an empty TX FIFO, the copies are of the payload on top of it
+        self.rf24.flush_tx()?;
+        self.rf24.write_payload(payload)?;
+        self.rf24.reuse_tx_payload()?;
+
+        let mut delivery = if self.ack_payloads {
+            Delivery::Lost
+        } else {
+            Delivery::Unacknowledged
+        };
+
+        for _ in 0..copies {
+            self.rf24.set_ce_high();
+            sleep(Duration::from_micros(15));
+            self.rf24.set_ce_low();
+
+            // The payload stays in the TX FIFO after MAX_RT, ready for the next copy.
+            let status = self.wait_for_transmission()?;
+
+            if self.ack_payloads && status & TX_DS != 0 {
+                let retries = self.rf24.read_register(Register::OBSERVE_TX)? & ARC_CNT;
+                let mut payload = None;
+                if status & RX_DR != 0 {
+                    payload = self.read_dynamic_payload()?;
+                }
+                delivery = Delivery::Acked { retries, payload };
+            }
+
+            // Clear Status
+            let status: u8 = RX_DR | TX_DS | MAX_RT;
+            self.rf24.write_register(Register::STATUS, status)?;
+        }
+
+        // Ends the reuse, so the next payload doesn't go after this one
+        self.rf24.flush_tx()?;
+
+        Ok(delivery)
+    }
+
+    pub fn timeouts(&self) -> Timeouts {
+        self.timeouts
+    }
+
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Reads and decodes every register of the module.
+    pub fn register_snapshot(&self) -> Result<RegisterSnapshot, RF24Error> {
+        RegisterSnapshot::read(&self.rf24)
+    }
+}
+
+/// The PWR_UP and PRIM_RX bits of CONFIG for each state.
+fn state_bits(state: PowerState) -> u8 {
+    match state {
+        PowerState::PowerDown => 0,
+        PowerState::StandbyI | PowerState::Tx => PWR_UP,
+        PowerState::Rx => PWR_UP | PRIM_RX,
+    }
+}
+
+/// Rejects the combinations of settings the datasheet doesn't allow.
+fn check_settings(
+    rate: DataRate,
+    crc: Crc,
+    ack_payloads: bool,
+    retransmit: Retransmit,
+) -> Result<(), RF24Error> {
+    if ack_payloads && crc == Crc::Disabled {
+        return Err(RF24Error::IncompatibleSettings(
+            "auto-ack needs the CRC enabled",
+        ));
+    }
+    if rate == DataRate::_250Kbps && retransmit.delay_us < 500 {
+        return Err(RF24Error::IncompatibleSettings(
+            "the retransmit delay must be at least 500µs at 250Kbps",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::{configured_radio, MockBus};
+    use super::*;
+
+    fn radio(bus: &MockBus, ack_payloads: bool) -> Radio {
+        let mut radio = configured_radio(bus);
+        if ack_payloads {
+            radio.set_ack_payloads(true).unwrap();
+            radio.configure().unwrap();
+        }
+        radio
+    }
+
+    #[test]
+    fn sends_without_waiting_for_acks_by_default() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, false);
+
+        assert_eq!(radio.send(&[1, 2, 3]).unwrap(), Delivery::Unacknowledged);
+        assert_eq!(bus.transmitted(), vec![vec![1, 2, 3]]);
+    }
+
+    #[test]
+    fn returns_the_ack_payload() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, true);
+        bus.chip
+            .lock()
+            .unwrap()
+            .ack_payloads
+            .push_back(vec![9, 8, 7]);
+
+        assert_eq!(
+            radio.send(&[1]).unwrap(),
+            Delivery::Acked {
+                retries: 0,
+                payload: Some(vec![9, 8, 7]),
+            }
+        );
+        assert_eq!(
+            radio.send(&[2]).unwrap(),
+            Delivery::Acked {
+                retries: 0,
+                payload: None,
+            }
+        );
+    }
+
+    #[test]
+    fn drops_payloads_that_were_not_acknowledged() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, true);
+        bus.chip.lock().unwrap().in_range = false;
+
+        assert_eq!(radio.send(&[1]).unwrap(), Delivery::Lost);
+        assert!(bus.chip.lock().unwrap().tx_fifo.is_empty());
+
+        bus.chip.lock().unwrap().in_range = true;
+        assert!(matches!(radio.send(&[2]).unwrap(), Delivery::Acked { .. }));
+        assert_eq!(bus.transmitted(), vec![vec![2]]);
+    }
+
+    #[test]
+    fn sends_bursts_and_copies() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, true);
+        bus.chip
+            .lock()
+            .unwrap()
+            .ack_payloads
+            .extend([vec![7], vec![8]]);
+
+        let burst = [
+            TxPayload {
+                data: &[1],
+                no_ack: false,
+            },
+            TxPayload {
+                data: &[2],
+                no_ack: true,
+            },
+            TxPayload {
+                data: &[3],
+                no_ack: false,
+            },
+        ];
+        assert_eq!(
+            radio.send_burst(&burst).unwrap(),
+            Delivery::Acked {
+                retries: 0,
+                payload: Some(vec![8]),
+            }
+        );
+        assert_eq!(bus.transmitted(), vec![vec![1], vec![2], vec![3]]);
+        assert_eq!(radio.power_state(), PowerState::StandbyI);
+        assert!(!bus.chip.lock().unwrap().ce);
+
+        assert!(matches!(
+            radio.send_burst(&[burst[0]; 4]),
+            Err(RF24Error::BurstTooLong)
+        ));
+
+        // Out of range, only the payloads that don't need an ACK get through
+        bus.chip.lock().unwrap().in_range = false;
+        assert_eq!(radio.send_burst(&burst[1..]).unwrap(), Delivery::Lost);
+        assert_eq!(bus.transmitted().last(), Some(&vec![2]));
+        assert!(bus.chip.lock().unwrap().tx_fifo.is_empty());
+
+        assert_eq!(radio.send_copies(&[4], 3).unwrap(), Delivery::Lost);
+        bus.chip.lock().unwrap().in_range = true;
+        assert!(matches!(
+            radio.send_copies(&[5], 3).unwrap(),
+            Delivery::Acked { .. }
+        ));
+        assert_eq!(bus.transmitted()[4..], [vec![5], vec![5], vec![5]]);
+        assert!(bus.chip.lock().unwrap().tx_fifo.is_empty());
+        assert!(!bus.chip.lock().unwrap().reuse);
+    }
+
+    #[test]
+    fn gives_up_on_a_chip_that_never_sends() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, false);
+        radio.set_timeouts(Timeouts {
+            tx_fifo: Duration::from_millis(5),
+            transmission: Duration::from_millis(5),
+        });
+        bus.chip.lock().unwrap().ce_stuck = true;
+
+        for payload in 0..TX_FIFO_SIZE as u8 {
+            assert_eq!(radio.send(&[payload]).unwrap(), Delivery::Unacknowledged);
+        }
+        assert!(matches!(
+            radio.send(&[3]),
+            Err(RF24Error::Timeout("room in the TX FIFO"))
+        ));
+        assert!(bus.chip.lock().unwrap().tx_fifo.is_empty());
+
+        radio.set_ack_payloads(true).unwrap();
+        radio.configure().unwrap();
+        assert!(matches!(
+            radio.send(&[4]),
+            Err(RF24Error::Timeout("the transmission"))
+        ));
+        assert!(matches!(
+            radio.send_burst(&[TxPayload {
+                data: &[5],
+                no_ack: false,
+            }]),
+            Err(RF24Error::Timeout("the burst"))
+        ));
+        assert_eq!(radio.power_state(), PowerState::StandbyI);
+        assert!(bus.chip.lock().unwrap().tx_fifo.is_empty());
+        assert!(bus.transmitted().is_empty());
+
+        // An unplugged chip reads as 0xFF, a full TX FIFO
+        bus.chip.lock().unwrap().connected = false;
+        assert!(matches!(
+            radio.send(&[6]),
+            Err(RF24Error::Timeout("room in the TX FIFO"))
+        ));
+    }
+
+    #[test]
+    fn receives_without_acknowledging() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, true);
+        radio.set_passive(true);
+        radio.configure().unwrap();
+        radio.start_listening().unwrap();
+        assert_eq!(bus.chip.lock().unwrap().register(Register::EN_AA), 0);
+
+        assert_eq!(radio.receive().unwrap(), None);
+        bus.chip
+            .lock()
+            .unwrap()
+            .rx_fifo
+            .extend([vec![1; 13], vec![2; 5]]);
+        assert_eq!(radio.receive().unwrap(), Some(vec![1; 13]));
+        assert_eq!(radio.receive().unwrap(), Some(vec![2; 5]));
+        assert_eq!(radio.receive().unwrap(), None);
+        assert!(radio.verify_configuration().is_ok());
+    }
+
+    #[test]
+    fn transmits_a_carrier_wave() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, false);
+
+        radio.start_carrier_wave(40, PowerLevel::_12dBm).unwrap();
+        {
+            let chip = bus.chip.lock().unwrap();
+            assert_eq!(
+                chip.register(Register::RF_SETUP),
+                CONT_WAVE | PLL_LOCK | PowerLevel::_12dBm as u8
+            );
+            assert_eq!(chip.register(Register::RF_CH), 40);
+            assert!(chip.ce);
+        }
+        assert!(radio.forced_pll_lock_set(40).unwrap());
+        assert!(!radio.forced_pll_lock_set(41).unwrap());
+
+        radio.stop_carrier_wave().unwrap();
+        assert!(!bus.chip.lock().unwrap().ce);
+        assert_eq!(radio.channel(), 125);
+        assert!(radio.verify_configuration().is_ok());
+    }
+
+    #[test]
+    fn changes_the_rf_parameters() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, true);
+
+        radio.set_address("abc").unwrap();
+        radio.set_crc(Crc::OneByte).unwrap();
+        radio.set_retransmit(1500, 15).unwrap();
+        radio.set_data_rate(DataRate::_2Mbps).unwrap();
+        radio.set_power_level(PowerLevel::_18dBm).unwrap();
+
+        let snapshot = radio.register_snapshot().unwrap();
+        assert_eq!(snapshot.address_width, Some(3));
+        assert_eq!(snapshot.tx_addr[..3], *b"abc");
+        assert_eq!(snapshot.config.crc, Crc::OneByte);
+        assert!(snapshot.config.power_up);
+        assert_eq!(
+            snapshot.retransmit,
+            Retransmit {
+                delay_us: 1500,
+                count: 15,
+            }
+        );
+        assert_eq!(snapshot.rf_setup.data_rate, Some(DataRate::_2Mbps));
+        assert_eq!(snapshot.rf_setup.power_level, PowerLevel::_18dBm);
+
+        radio.configure().unwrap();
+        assert_eq!(radio.register_snapshot().unwrap(), snapshot);
+    }
+
+    #[test]
+    fn rejects_illegal_rf_parameters() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, true);
+
+        assert!(matches!(
+            radio.set_address("abcdef"),
+            Err(RF24Error::InvalidAddressWidth)
+        ));
+        assert!(matches!(
+            radio.set_retransmit(300, 3),
+            Err(RF24Error::InvalidRetransmit)
+        ));
+        assert!(matches!(
+            radio.set_retransmit(500, 16),
+            Err(RF24Error::InvalidRetransmit)
+        ));
+        assert!(matches!(
+            radio.set_retransmit(250, 3),
+            Err(RF24Error::IncompatibleSettings(_))
+        ));
+        assert!(matches!(
+            radio.set_crc(Crc::Disabled),
+            Err(RF24Error::IncompatibleSettings(_))
+        ));
+
+        radio.set_ack_payloads(false).unwrap();
+        radio.set_crc(Crc::Disabled).unwrap();
+        assert!(matches!(
+            radio.set_ack_payloads(true),
+            Err(RF24Error::IncompatibleSettings(_))
+        ));
+
+        radio.set_data_rate(DataRate::_1Mbps).unwrap();
+        radio.set_retransmit(250, 3).unwrap();
+        assert!(matches!(
+            radio.set_data_rate(DataRate::_250Kbps),
+            Err(RF24Error::IncompatibleSettings(_))
+        ));
+        assert_eq!(radio.data_rate(), DataRate::_1Mbps);
+    }
+
+    #[test]
+    fn goes_through_the_power_states() {
+        let bus = MockBus::new();
+        let mut radio = radio(&bus, false);
+        let config = |bus: &MockBus| bus.chip.lock().unwrap().register(Register::CONFIG);
+        let ce = |bus: &MockBus| bus.chip.lock().unwrap().ce;
+
+        assert_eq!(radio.power_state(), PowerState::StandbyI);
+
+        radio.set_power_state(PowerState::PowerDown).unwrap();
+        assert_eq!(config(&bus) & (PWR_UP | PRIM_RX), 0);
+        assert!(!ce(&bus));
+        assert!(radio.verify_configuration().is_ok());
+
+        radio.set_power_state(PowerState::Rx).unwrap();
+        assert_eq!(config(&bus) & (PWR_UP | PRIM_RX), PWR_UP | PRIM_RX);
+        assert!(ce(&bus));
+
+        radio.set_power_state(PowerState::StandbyI).unwrap();
+        assert_eq!(config(&bus) & (PWR_UP | PRIM_RX), PWR_UP);
+        assert!(!ce(&bus));
+
+        // Sending wakes the radio up
+        radio.set_power_state(PowerState::PowerDown).unwrap();
+        radio.send(&[1]).unwrap();
+        assert_eq!(radio.power_state(), PowerState::StandbyI);
+        assert_eq!(bus.transmitted(), vec![vec![1]]);
+    }
+
+    #[test]
+    fn detects_a_missing_chip() {
+        let bus = MockBus::new();
+        bus.chip.lock().unwrap().connected = false;
+        let mut radio = Radio::with_bus(
+            Box::new(bus.clone()),
+            "aaaaa",
+            DataRate::_250Kbps,
+            PowerLevel::_0dBm,
+            125,
+        )
+        .unwrap();
+
+        assert!(!radio.is_chip_connected().unwrap());
+        assert!(matches!(
+            radio.configure(),
+            Err(RF24Error::ChipNotConnected)
+        ));
+    }
+
+    #[test]
+    fn reports_the_register_that_did_not_stick() {
+        let bus = MockBus::new();
+        bus.chip.lock().unwrap().stuck = Some(Register::RF_SETUP);
+        let mut radio = Radio::with_bus(
+            Box::new(bus.clone()),
+            "aaaaa",
+            DataRate::_250Kbps,
+            PowerLevel::_0dBm,
+            125,
+        )
+        .unwrap();
+
+        assert!(radio.is_chip_connected().unwrap());
+        assert!(matches!(
+            radio.configure(),
+            Err(RF24Error::RegisterMismatch {
+                register: Register::RF_SETUP,
+                written: 0x26,
+                read: 0x0E,
+            })
+        ));
     }
 }
@@ -0,0 +1,119 @@
+//! Setting up a `Radio` on the Raspberry Pi, one option at a time.
+
+use super::spi_bus::MAX_SPI_SPEED;
+use super::*;
+use rppal::spi;
+
+/// The settings of a `Radio` wired to the Raspberry Pi SPI, see `Radio::builder`.
+///
+/// The defaults are the ones the robot firmware is built with: 250Kbps, 0dBm and channel 125,
+/// on SPI0 with CE0 as chip select and GPIO 25 as CE.
+#[derive(Debug, Clone)]
+pub struct RadioBuilder {
+    address: String,
+    rate: DataRate,
+    power_level: PowerLevel,
+    channel: u8,
+    spi: SpiConfig,
+}
+
+impl RadioBuilder {
+    pub(super) fn new(address: &str) -> Self {
+        RadioBuilder {
+            address: address.to_string(),
+            rate: DataRate::_250Kbps,
+            power_level: PowerLevel::_0dBm,
+            channel: 125,
+            spi: SpiConfig::default(),
+        }
+    }
+
+    pub fn data_rate(mut self, rate: DataRate) -> Self {
+        self.rate = rate;
+        self
+    }
+
+    pub fn power_level(mut self, power_level: PowerLevel) -> Self {
+        self.power_level = power_level;
+        self
+    }
+
+    pub fn channel(mut self, channel: u8) -> Self {
+        self.channel = channel;
+        self
+    }
+
+    /// Sets every wiring option at once.
+    pub fn spi(mut self, spi: SpiConfig) -> Self {
+        self.spi = spi;
+        self
+    }
+
+    pub fn spi_bus(mut self, bus: spi::Bus) -> Self {
+        self.spi.bus = bus;
+        self
+    }
+
+    pub fn slave_select(mut self, slave_select: spi::SlaveSelect) -> Self {
+        self.spi.slave_select = slave_select;
+        self
+    }
+
+    /// SPI clock speed in Hz, up to 10MHz. Long wires may need a slower clock.
+    pub fn clock_speed(mut self, clock_speed: u32) -> Self {
+        self.spi.clock_speed = clock_speed;
+        self
+    }
+
+    /// Drives chip select with a GPIO instead of the slave select line of the bus, to put
+    /// more modules on a bus than it has slave select lines.
+    pub fn cs_pin(mut self, pin: u8) -> Self {
+        self.spi.cs_pin = Some(pin);
+        self
+    }
+
+    pub fn ce_pin(mut self, pin: u8) -> Self {
+        self.spi.ce_pin = pin;
+        self
+    }
+
+    /// Opens the SPI bus and the GPIOs. The module still has to be configured.
+    pub fn build(self) -> Result<Radio, RF24Error> {
+        if self.spi.clock_speed > MAX_SPI_SPEED {
+            return Err(RF24Error::IncompatibleSettings(
+                "the SPI clock must be at most 10MHz",
+            ));
+        }
+        if self.spi.cs_pin == Some(self.spi.ce_pin) {
+            return Err(RF24Error::IncompatibleSettings(
+                "chip select and CE need their own pins",
+            ));
+        }
+
+        let rf24 = RF24::new(&self.spi)?;
+        Radio::with_rf24(
+            rf24,
+            &self.address,
+            self.rate,
+            self.power_level,
+            self.channel,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_wiring_the_module_cannot_use() {
+        let too_fast = Radio::builder("aaaaa").clock_speed(20_000_000).build();
+        assert!(matches!(too_fast, Err(RF24Error::IncompatibleSettings(_))));
+
+        let shared_pin = Radio::builder("aaaaa").cs_pin(25).ce_pin(25).build();
+        assert!(matches!(
+            shared_pin,
+            Err(RF24Error::IncompatibleSettings(_))
+        ));
+    }
+}
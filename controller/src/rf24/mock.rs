@@ -0,0 +1,328 @@
+//! An in-memory NRF24L01+ used to exercise the driver without hardware.
+
+use super::*;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// The register file and FIFOs of the simulated chip.
+#[derive(Debug)]
+pub struct MockChip {
+    pub registers: [u8; 0x1E],
+    pub addresses: [[u8; 5]; 7],
+    /// The payloads waiting to be sent, and whether they were written without ACK.
+    pub tx_fifo: VecDeque<(Vec<u8>, bool)>,
+    pub rx_fifo: VecDeque<Vec<u8>>,
+    /// Every payload that left the TX FIFO while CE was high, in order.
+    pub transmitted: Vec<Vec<u8>>,
+    /// Payloads the receiver attaches to its next ACKs.
+    pub ack_payloads: VecDeque<Vec<u8>>,
+    /// Whether a receiver is around to acknowledge the payloads.
+    pub in_range: bool,
+    /// Whether the chip is on the bus at all. When it isn't, MISO floats high.
+    pub connected: bool,
+    /// A register that ignores writes, like on a faulty module.
+    pub stuck: Option<Register>,
+    /// Channels with something else transmitting on them, seen by the received power detector.
+    pub busy_channels: Vec<u8>,
+    /// Whether the CE line is broken, so the chip never sends and its TX FIFO fills up.
+    pub ce_stuck: bool,
+    /// Whether the payload on top of the TX FIFO is kept after it's sent, after `REUSE_TX_PL`.
+    pub reuse: bool,
+    pub ce: bool,
+}
+
+impl MockChip {
+    /// A chip with the reset values from the datasheet.
+    pub fn new() -> Self {
+        let mut registers = [0u8; 0x1E];
+        registers[Register::CONFIG as usize] = EN_CRC;
+        registers[Register::EN_AA as usize] = 0x3F;
+        registers[Register::EN_RXADDR as usize] = 0x03;
+        registers[Register::SETUP_AW as usize] = 0x03;
+        registers[Register::SETUP_RETR as usize] = 0x03;
+        registers[Register::RF_CH as usize] = 0x02;
+        registers[Register::RF_SETUP as usize] = 0x0E;
+        registers[Register::STATUS as usize] = 0x0E;
+        registers[Register::RX_ADDR_P2 as usize] = 0xC3;
+        registers[Register::RX_ADDR_P3 as usize] = 0xC4;
+        registers[Register::RX_ADDR_P4 as usize] = 0xC5;
+        registers[Register::RX_ADDR_P5 as usize] = 0xC6;
+        registers[Register::FIFO_STATUS as usize] = TX_EMPTY | RX_EMPTY;
+
+        MockChip {
+            registers,
+            addresses: [
+                [0xE7; 5], [0xC2; 5], [0; 5], [0; 5], [0; 5], [0; 5], [0xE7; 5],
+            ],
+            tx_fifo: VecDeque::new(),
+            rx_fifo: VecDeque::new(),
+            transmitted: Vec::new(),
+            ack_payloads: VecDeque::new(),
+            in_range: true,
+            connected: true,
+            stuck: None,
+            busy_channels: Vec::new(),
+            ce_stuck: false,
+            reuse: false,
+            ce: false,
+        }
+    }
+
+    pub fn register(&self, reg: Register) -> u8 {
+        self.value(reg as u8)
+    }
+
+    fn value(&self, reg: u8) -> u8 {
+        match reg {
+            r if r == Register::STATUS as u8 => self.status(),
+            r if r == Register::FIFO_STATUS as u8 => self.fifo_status(),
+            r if r == Register::RPD as u8 => self.rpd(),
+            _ => self.registers[reg as usize],
+        }
+    }
+
+    fn status(&self) -> u8 {
+        let full = if self.tx_fifo.len() >= TX_FIFO_SIZE {
+            TX_FULL
+        } else {
+            0
+        };
+        (self.registers[Register::STATUS as usize] & !TX_FULL) | full
+    }
+
+    fn rpd(&self) -> u8 {
+        let config = self.registers[Register::CONFIG as usize];
+        let listening = self.ce && config & PWR_UP != 0 && config & PRIM_RX != 0;
+        let channel = self.registers[Register::RF_CH as usize];
+
+        if listening && self.busy_channels.contains(&channel) {
+            RPD
+        } else {
+            0
+        }
+    }
+
+    fn fifo_status(&self) -> u8 {
+        let mut fifo_status = self.registers[Register::FIFO_STATUS as usize]
+            & !(TX_REUSE | FIFO_FULL | TX_EMPTY | RX_EMPTY);
+        if self.reuse {
+            fifo_status |= TX_REUSE;
+        }
+        if self.tx_fifo.len() >= TX_FIFO_SIZE {
+            fifo_status |= FIFO_FULL;
+        }
+        if self.tx_fifo.is_empty() {
+            fifo_status |= TX_EMPTY;
+        }
+        if self.rx_fifo.is_empty() {
+            fifo_status |= RX_EMPTY;
+        }
+        fifo_status
+    }
+
+    fn address_index(reg: u8) -> Option<usize> {
+        match reg {
+            r if r == Register::RX_ADDR_P0 as u8 => Some(0),
+            r if r == Register::RX_ADDR_P1 as u8 => Some(1),
+            r if r == Register::TX_ADDR as u8 => Some(6),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, reg: u8, data: &[u8]) {
+        if let Some(index) = MockChip::address_index(reg) {
+            self.addresses[index][..data.len()].copy_from_slice(data);
+            return;
+        }
+
+        let Some(&value) = data.first() else {
+            return;
+        };
+
+        if self.stuck.is_some_and(|stuck| stuck as u8 == reg) {
+            return;
+        }
+
+        if reg == Register::STATUS as u8 {
+            // Interrupt flags are cleared by writing 1 to them.
+            self.registers[reg as usize] &= !(value & (RX_DR | TX_DS | MAX_RT));
+        } else {
+            self.registers[reg as usize] = value;
+        }
+    }
+
+    fn read(&self, reg: u8, data: &mut [u8]) {
+        if let Some(index) = MockChip::address_index(reg) {
+            let size = data.len().min(5);
+            data[..size].copy_from_slice(&self.addresses[index][..size]);
+            return;
+        }
+
+        if let Some(value) = data.first_mut() {
+            *value = self.value(reg);
+        }
+    }
+
+    /// Runs a single SPI transaction, filling `read` like the chip would.
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) {
+        let Some(&command) = write.first() else {
+            return;
+        };
+
+        if !self.connected {
+            read.fill(0xFF);
+            return;
+        }
+
+        read.fill(0);
+        read[0] = self.status();
+
+        let data = &write[1..];
+        match command {
+            c if c & 0xE0 == Command::W_REGISTER as u8 => self.write(c & 0x1F, data),
+            c if c & 0xE0 == Command::R_REGISTER as u8 => self.read(c & 0x1F, &mut read[1..]),
+            c if c == Command::W_TX_PAYLOAD as u8 => self.push_payload(data, false),
+            c if c == Command::W_TX_PAYLOAD_NOACK as u8
+                && self.registers[Register::FEATURE as usize] & EN_DYN_ACK != 0 =>
+            {
+                self.push_payload(data, true)
+            }
+            c if c == Command::REUSE_TX_PL as u8 => self.reuse = true,
+            c if c == Command::FLUSH_TX as u8 => {
+                self.tx_fifo.clear();
+                self.reuse = false;
+            }
+            c if c == Command::FLUSH_RX as u8 => self.rx_fifo.clear(),
+            c if c == Command::R_RX_PL_WID as u8 => {
+                read[1] = self
+                    .rx_fifo
+                    .front()
+                    .map_or(0, |payload| payload.len() as u8);
+            }
+            c if c == Command::R_RX_PAYLOAD as u8 => {
+                if let Some(payload) = self.rx_fifo.pop_front() {
+                    let size = payload.len().min(read.len() - 1);
+                    read[1..=size].copy_from_slice(&payload[..size]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn push_payload(&mut self, data: &[u8], no_ack: bool) {
+        self.reuse = false;
+        if self.tx_fifo.len() < TX_FIFO_SIZE {
+            self.tx_fifo.push_back((data.to_vec(), no_ack));
+        }
+    }
+
+    fn set_ce(&mut self, high: bool) {
+        if self.ce_stuck {
+            return;
+        }
+
+        let config = self.registers[Register::CONFIG as usize];
+        let transmitter = config & PWR_UP != 0 && config & PRIM_RX == 0;
+
+        if high && !self.ce && transmitter {
+            self.transmit();
+        }
+
+        self.ce = high;
+    }
+
+    fn transmit(&mut self) {
+        let retries = self.registers[Register::SETUP_RETR as usize] & ARC;
+
+        while let Some((payload, no_ack)) = self.tx_fifo.front() {
+            // The chip stops transmitting until MAX_RT is cleared.
+            if self.registers[Register::STATUS as usize] & MAX_RT != 0 {
+                return;
+            }
+
+            let auto_ack = self.registers[Register::EN_AA as usize] & ENAA_P0 != 0 && !no_ack;
+            if auto_ack && !self.in_range {
+                let lost = (self.registers[Register::OBSERVE_TX as usize] & PLOS_CNT) >> 4;
+                self.registers[Register::OBSERVE_TX as usize] = ((lost + 1).min(15) << 4) | retries;
+                self.registers[Register::STATUS as usize] |= MAX_RT;
+                return;
+            }
+
+            self.transmitted.push(payload.clone());
+            self.registers[Register::STATUS as usize] |= TX_DS;
+
+            if auto_ack {
+                self.registers[Register::OBSERVE_TX as usize] &= PLOS_CNT;
+                if let Some(ack) = self.ack_payloads.pop_front() {
+                    self.rx_fifo.push_back(ack);
+                    self.registers[Register::STATUS as usize] |= RX_DR;
+                }
+            }
+
+            // A reused payload is sent once per CE pulse.
+            if self.reuse {
+                return;
+            }
+            self.tx_fifo.pop_front();
+        }
+    }
+}
+
+impl Default for MockChip {
+    fn default() -> Self {
+        MockChip::new()
+    }
+}
+
+/// A `Bus` backed by a `MockChip`.
+///
+/// Clones share the same chip, so a test can keep one to inspect what the driver did.
+#[derive(Debug, Clone)]
+pub struct MockBus {
+    pub chip: Arc<Mutex<MockChip>>,
+}
+
+impl MockBus {
+    pub fn new() -> Self {
+        MockBus {
+            chip: Arc::new(Mutex::new(MockChip::new())),
+        }
+    }
+
+    pub fn transmitted(&self) -> Vec<Vec<u8>> {
+        self.chip.lock().unwrap().transmitted.clone()
+    }
+}
+
+impl Default for MockBus {
+    fn default() -> Self {
+        MockBus::new()
+    }
+}
+
+impl Bus for MockBus {
+    fn transfer(&self, read: &mut [u8], write: &[u8]) -> Result<usize, RF24Error> {
+        self.chip.lock().unwrap().transfer(read, write);
+        Ok(write.len())
+    }
+
+    fn set_ce(&mut self, high: bool) {
+        self.chip.lock().unwrap().set_ce(high);
+    }
+}
+
+/// A radio on `bus` with the address, data rate and channel of the robot, configured.
+///
+/// Settings changed afterwards, like ACK payloads, take effect on the next `configure`.
+pub fn configured_radio(bus: &MockBus) -> Radio {
+    let mut radio = Radio::with_bus(
+        Box::new(bus.clone()),
+        "aaaaa",
+        DataRate::_250Kbps,
+        PowerLevel::_0dBm,
+        125,
+    )
+    .unwrap();
+    radio.configure().unwrap();
+    radio
+}
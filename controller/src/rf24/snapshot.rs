@@ -0,0 +1,421 @@
+//! A decoded copy of every register of the NRF24L01+, to compare the configuration of two radios.
+
+use super::*;
+use serde::Serialize;
+
+/// Length of the CRC appended to each packet.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub enum Crc {
+    Disabled,
+    OneByte,
+    TwoBytes,
+}
+
+impl Crc {
+    /// The EN_CRC and CRCO bits of CONFIG.
+    pub(super) fn config_bits(&self) -> u8 {
+        match *self {
+            Crc::Disabled => 0,
+            Crc::OneByte => EN_CRC,
+            Crc::TwoBytes => EN_CRC | CRCO,
+        }
+    }
+}
+
+impl fmt::Display for Crc {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Crc::Disabled => write!(f, "disabled"),
+            Crc::OneByte => write!(f, "1 byte"),
+            Crc::TwoBytes => write!(f, "2 bytes"),
+        }
+    }
+}
+
+/// The CONFIG register, decoded.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Config {
+    pub mask_rx_dr: bool,
+    pub mask_tx_ds: bool,
+    pub mask_max_rt: bool,
+    pub crc: Crc,
+    pub power_up: bool,
+    pub primary_rx: bool,
+}
+
+impl From<u8> for Config {
+    fn from(register: u8) -> Self {
+        let crc = match (register & EN_CRC != 0, register & CRCO != 0) {
+            (false, _) => Crc::Disabled,
+            (true, false) => Crc::OneByte,
+            (true, true) => Crc::TwoBytes,
+        };
+
+        Config {
+            mask_rx_dr: register & MASK_RX_DR != 0,
+            mask_tx_ds: register & MASK_TX_DS != 0,
+            mask_max_rt: register & MASK_MAX_RT != 0,
+            crc,
+            power_up: register & PWR_UP != 0,
+            primary_rx: register & PRIM_RX != 0,
+        }
+    }
+}
+
+/// The SETUP_RETR register, decoded.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Retransmit {
+    pub delay_us: u16,
+    pub count: u8,
+}
+
+impl Retransmit {
+    pub(super) fn register(&self) -> u8 {
+        let delay = (self.delay_us / 250 - 1) as u8;
+        ((delay << 4) & ARD) | (self.count & ARC)
+    }
+}
+
+impl From<u8> for Retransmit {
+    fn from(register: u8) -> Self {
+        Retransmit {
+            delay_us: (((register & ARD) >> 4) as u16 + 1) * 250,
+            count: register & ARC,
+        }
+    }
+}
+
+/// The RF_SETUP register, decoded.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct RfSetup {
+    pub continuous_wave: bool,
+    /// `None` when both RF_DR_LOW and RF_DR_HIGH are set, which is reserved.
+    pub data_rate: Option<DataRate>,
+    pub pll_lock: bool,
+    pub power_level: PowerLevel,
+}
+
+impl From<u8> for RfSetup {
+    fn from(register: u8) -> Self {
+        let data_rate = match (register & RF_DR_LOW != 0, register & RF_DR_HIGH != 0) {
+            (false, false) => Some(DataRate::_1Mbps),
+            (false, true) => Some(DataRate::_2Mbps),
+            (true, false) => Some(DataRate::_250Kbps),
+            (true, true) => None,
+        };
+
+        let power_level = match register & RF_PWR {
+            0x00 => PowerLevel::_18dBm,
+            0x02 => PowerLevel::_12dBm,
+            0x04 => PowerLevel::_6dBm,
+            _ => PowerLevel::_0dBm,
+        };
+
+        RfSetup {
+            continuous_wave: register & CONT_WAVE != 0,
+            data_rate,
+            pll_lock: register & PLL_LOCK != 0,
+            power_level,
+        }
+    }
+}
+
+/// The OBSERVE_TX register, decoded.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct ObserveTx {
+    pub lost_packets: u8,
+    pub retransmits: u8,
+}
+
+impl From<u8> for ObserveTx {
+    fn from(register: u8) -> Self {
+        ObserveTx {
+            lost_packets: (register & PLOS_CNT) >> 4,
+            retransmits: register & ARC_CNT,
+        }
+    }
+}
+
+/// The FEATURE register, decoded.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Feature {
+    pub dynamic_payloads: bool,
+    pub ack_payloads: bool,
+    pub dynamic_ack: bool,
+}
+
+impl From<u8> for Feature {
+    fn from(register: u8) -> Self {
+        Feature {
+            dynamic_payloads: register & EN_DPL != 0,
+            ack_payloads: register & EN_ACK_PAY != 0,
+            dynamic_ack: register & EN_DYN_ACK != 0,
+        }
+    }
+}
+
+/// One flag per data pipe, for the registers with a bit for each of them.
+fn pipes(register: u8) -> [bool; 6] {
+    std::array::from_fn(|pipe| register & (1 << pipe) != 0)
+}
+
+/// Every register of the module, read at once and decoded.
+///
+/// It implements `Serialize`, so snapshots of a working and a broken radio can be diffed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RegisterSnapshot {
+    pub config: Config,
+    pub auto_ack: [bool; 6],
+    pub rx_pipes: [bool; 6],
+    /// Address width in bytes, `None` for the illegal value 0.
+    pub address_width: Option<u8>,
+    pub retransmit: Retransmit,
+    pub channel: u8,
+    pub rf_setup: RfSetup,
+    pub status: Status,
+    pub observe_tx: ObserveTx,
+    pub received_power_detector: bool,
+    pub rx_addr_p0: [u8; 5],
+    pub rx_addr_p1: [u8; 5],
+    /// Last byte of the addresses of pipes 2 to 5, the rest is shared with pipe 1.
+    pub rx_addr_p2_p5: [u8; 4],
+    pub tx_addr: [u8; 5],
+    pub rx_payload_widths: [u8; 6],
+    pub fifo_status: FifoStatus,
+    pub dynamic_payloads: [bool; 6],
+    pub feature: Feature,
+}
+
+impl RegisterSnapshot {
+    pub(super) fn read(rf24: &RF24) -> Result<RegisterSnapshot, RF24Error> {
+        let address = |reg| -> Result<[u8; 5], RF24Error> {
+            let mut address = [0u8; 5];
+            rf24.read_address(reg, 5, &mut address)?;
+            Ok(address)
+        };
+
+        let address_width = match rf24.read_register(Register::SETUP_AW)? & AW {
+            0 => None,
+            width => Some(width + 2),
+        };
+
+        Ok(RegisterSnapshot {
+            config: Config::from(rf24.read_register(Register::CONFIG)?),
+            auto_ack: pipes(rf24.read_register(Register::EN_AA)?),
+            rx_pipes: pipes(rf24.read_register(Register::EN_RXADDR)?),
+            address_width,
+            retransmit: Retransmit::from(rf24.read_register(Register::SETUP_RETR)?),
+            channel: rf24.read_register(Register::RF_CH)? & RF_CH,
+            rf_setup: RfSetup::from(rf24.read_register(Register::RF_SETUP)?),
+            status: Status::from(rf24.read_register(Register::STATUS)?),
+            observe_tx: ObserveTx::from(rf24.read_register(Register::OBSERVE_TX)?),
+            received_power_detector: rf24.read_register(Register::RPD)? & RPD != 0,
+            rx_addr_p0: address(Register::RX_ADDR_P0)?,
+            rx_addr_p1: address(Register::RX_ADDR_P1)?,
+            rx_addr_p2_p5: [
+                rf24.read_register(Register::RX_ADDR_P2)?,
+                rf24.read_register(Register::RX_ADDR_P3)?,
+                rf24.read_register(Register::RX_ADDR_P4)?,
+                rf24.read_register(Register::RX_ADDR_P5)?,
+            ],
+            tx_addr: address(Register::TX_ADDR)?,
+            rx_payload_widths: [
+                rf24.read_register(Register::RX_PW_P0)? & RX_PW_P0,
+                rf24.read_register(Register::RX_PW_P1)? & RX_PW_P1,
+                rf24.read_register(Register::RX_PW_P2)? & RX_PW_P2,
+                rf24.read_register(Register::RX_PW_P3)? & RX_PW_P3,
+                rf24.read_register(Register::RX_PW_P4)? & RX_PW_P4,
+                rf24.read_register(Register::RX_PW_P5)? & RX_PW_P5,
+            ],
+            fifo_status: FifoStatus::from(rf24.read_register(Register::FIFO_STATUS)?),
+            dynamic_payloads: pipes(rf24.read_register(Register::DYNPD)?),
+            feature: Feature::from(rf24.read_register(Register::FEATURE)?),
+        })
+    }
+}
+
+struct Hex<'a>(&'a [u8]);
+
+impl fmt::Display for Hex<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x")?;
+        for byte in self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+struct Pipes([bool; 6]);
+
+impl fmt::Display for Pipes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let enabled: Vec<String> = (0..6)
+            .filter(|pipe| self.0[*pipe])
+            .map(|pipe| format!("P{}", pipe))
+            .collect();
+        if enabled.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", enabled.join(" "))
+        }
+    }
+}
+
+impl fmt::Display for RegisterSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let config = &self.config;
+        writeln!(
+            f,
+            "CONFIG          = PWR_UP={} PRIM_RX={} CRC={} MASK_RX_DR={} MASK_TX_DS={} MASK_MAX_RT={}",
+            config.power_up as u8,
+            config.primary_rx as u8,
+            config.crc,
+            config.mask_rx_dr as u8,
+            config.mask_tx_ds as u8,
+            config.mask_max_rt as u8
+        )?;
+        writeln!(f, "EN_AA           = {}", Pipes(self.auto_ack))?;
+        writeln!(f, "EN_RXADDR       = {}", Pipes(self.rx_pipes))?;
+        match self.address_width {
+            Some(width) => writeln!(f, "SETUP_AW        = {} bytes", width)?,
+            None => writeln!(f, "SETUP_AW        = illegal")?,
+        }
+        writeln!(
+            f,
+            "SETUP_RETR      = ARD={}µs ARC={}",
+            self.retransmit.delay_us, self.retransmit.count
+        )?;
+        writeln!(f, "RF_CH           = {}", self.channel)?;
+
+        let rf_setup = &self.rf_setup;
+        match rf_setup.data_rate {
+            Some(rate) => write!(f, "RF_SETUP        = RF_DR={}", rate)?,
+            None => write!(f, "RF_SETUP        = RF_DR=reserved")?,
+        }
+        writeln!(
+            f,
+            " RF_PWR={} CONT_WAVE={} PLL_LOCK={}",
+            rf_setup.power_level, rf_setup.continuous_wave as u8, rf_setup.pll_lock as u8
+        )?;
+
+        let status = &self.status;
+        writeln!(
+            f,
+            "STATUS          = RX_DR={} TX_DS={} MAX_RT={} RX_P_NO={} TX_FULL={}",
+            status.rx_dr as u8,
+            status.tx_ds as u8,
+            status.max_rt as u8,
+            status.rx_p_no,
+            status.tx_full as u8
+        )?;
+        writeln!(
+            f,
+            "OBSERVE_TX      = PLOS_CNT={} ARC_CNT={}",
+            self.observe_tx.lost_packets, self.observe_tx.retransmits
+        )?;
+        writeln!(
+            f,
+            "RPD             = {}",
+            self.received_power_detector as u8
+        )?;
+        writeln!(
+            f,
+            "RX_ADDR_P0-1    = {} {}",
+            Hex(&self.rx_addr_p0),
+            Hex(&self.rx_addr_p1)
+        )?;
+        write!(f, "RX_ADDR_P2-5    =")?;
+        for address in self.rx_addr_p2_p5 {
+            write!(f, " {:#04x}", address)?;
+        }
+        writeln!(f)?;
+        writeln!(f, "TX_ADDR         = {}", Hex(&self.tx_addr))?;
+        write!(f, "RX_PW_P0-5      =")?;
+        for width in self.rx_payload_widths {
+            write!(f, " {}", width)?;
+        }
+        writeln!(f)?;
+
+        let fifo = &self.fifo_status;
+        writeln!(
+            f,
+            "FIFO_STATUS     = TX_REUSE={} TX_FULL={} TX_EMPTY={} RX_FULL={} RX_EMPTY={}",
+            fifo.tx_reuse as u8,
+            fifo.tx_full as u8,
+            fifo.tx_empty as u8,
+            fifo.rx_full as u8,
+            fifo.rx_empty as u8
+        )?;
+        writeln!(f, "DYNPD           = {}", Pipes(self.dynamic_payloads))?;
+        write!(
+            f,
+            "FEATURE         = EN_DPL={} EN_ACK_PAY={} EN_DYN_ACK={}",
+            self.feature.dynamic_payloads as u8,
+            self.feature.ack_payloads as u8,
+            self.feature.dynamic_ack as u8
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock::{configured_radio, MockBus};
+    use super::*;
+
+    #[test]
+    fn decodes_the_configuration() {
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        radio.set_address("abcde").unwrap();
+        radio.set_power_level(PowerLevel::_6dBm).unwrap();
+        radio.set_channel(76).unwrap();
+        radio.set_ack_payloads(true).unwrap();
+        radio.configure().unwrap();
+
+        let snapshot = radio.register_snapshot().unwrap();
+
+        assert_eq!(
+            snapshot.config,
+            Config {
+                mask_rx_dr: true,
+                mask_tx_ds: true,
+                mask_max_rt: true,
+                crc: Crc::TwoBytes,
+                power_up: true,
+                primary_rx: false,
+            }
+        );
+        assert_eq!(snapshot.auto_ack, [true, false, false, false, false, false]);
+        assert_eq!(snapshot.address_width, Some(5));
+        assert_eq!(
+            snapshot.retransmit,
+            Retransmit {
+                delay_us: 500,
+                count: 3,
+            }
+        );
+        assert_eq!(snapshot.channel, 76);
+        assert_eq!(snapshot.rf_setup.data_rate, Some(DataRate::_250Kbps));
+        assert_eq!(snapshot.rf_setup.power_level, PowerLevel::_6dBm);
+        assert_eq!(snapshot.rx_addr_p0, *b"abcde");
+        assert_eq!(snapshot.tx_addr, *b"abcde");
+        assert_eq!(snapshot.rx_payload_widths, [13; 6]);
+        assert!(snapshot.fifo_status.tx_empty);
+        assert_eq!(
+            snapshot.feature,
+            Feature {
+                dynamic_payloads: true,
+                ack_payloads: true,
+                dynamic_ack: true,
+            }
+        );
+
+        let json = serde_json::to_value(&snapshot).unwrap();
+        assert_eq!(json["channel"], 76);
+        assert_eq!(json["rf_setup"]["data_rate"], "250Kbps");
+        assert!(snapshot
+            .to_string()
+            .contains("RF_SETUP        = RF_DR=250Kbps RF_PWR=-6dBm"));
+    }
+}
@@ -0,0 +1,110 @@
+//! The module wired to the SPI and GPIO pins of a Raspberry Pi, built with the `rppal` feature.
+
+use super::{Bus, RF24Error, RF24};
+use rppal::{gpio, spi, spi::Spi};
+use std::cell::RefCell;
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Fastest SPI clock the nRF24 takes.
+pub(super) const MAX_SPI_SPEED: u32 = 10_000_000;
+
+/// Where the module is wired on the Raspberry Pi.
+#[derive(Debug, Copy, Clone)]
+pub struct SpiConfig {
+    pub bus: spi::Bus,
+    pub slave_select: spi::SlaveSelect,
+    /// Clock speed in Hz, up to 10MHz.
+    pub clock_speed: u32,
+    /// A GPIO driven as chip select, instead of the slave select line of the bus.
+    ///
+    /// The bus still drives its own slave select line, which has to be left unconnected.
+    pub cs_pin: Option<u8>,
+    pub ce_pin: u8,
+}
+
+impl Default for SpiConfig {
+    /// SPI0 with CE0 as chip select, and GPIO 25 as CE.
+    fn default() -> Self {
+        SpiConfig {
+            bus: spi::Bus::Spi0,
+            slave_select: spi::SlaveSelect::Ss0,
+            clock_speed: MAX_SPI_SPEED,
+            cs_pin: None,
+            ce_pin: 25,
+        }
+    }
+}
+
+/// A Raspberry Pi SPI peripheral plus a GPIO output for CE, and maybe one for chip select.
+#[derive(Debug)]
+pub struct SpiBus {
+    spi: Spi,
+    cs_pin: Option<RefCell<gpio::OutputPin>>,
+    ce_pin: gpio::OutputPin,
+}
+
+impl SpiBus {
+    pub fn new(config: &SpiConfig) -> Result<SpiBus, RF24Error> {
+        let gpio = gpio::Gpio::new().map_err(|e| RF24Error::GpioError(e.into()))?;
+        let ce_pin = gpio
+            .get(config.ce_pin)
+            .map_err(|e| RF24Error::GpioError(e.into()))?
+            .into_output_low();
+        let cs_pin = match config.cs_pin {
+            Some(pin) => Some(RefCell::new(
+                gpio.get(pin)
+                    .map_err(|e| RF24Error::GpioError(e.into()))?
+                    .into_output_high(),
+            )),
+            None => None,
+        };
+
+        let spi: Spi = Spi::new(
+            config.bus,
+            config.slave_select,
+            config.clock_speed,
+            spi::Mode::Mode0,
+        )
+        .map_err(|e| RF24Error::SpiError(e.into()))?;
+
+        sleep(Duration::from_millis(5));
+
+        Ok(SpiBus {
+            spi,
+            cs_pin,
+            ce_pin,
+        })
+    }
+}
+
+impl Bus for SpiBus {
+    fn transfer(&self, read: &mut [u8], write: &[u8]) -> Result<usize, RF24Error> {
+        // Each command is a single transfer, selected on its own
+        if let Some(cs_pin) = &self.cs_pin {
+            cs_pin.borrow_mut().set_low();
+        }
+        let transferred = self
+            .spi
+            .transfer(read, write)
+            .map_err(|e| RF24Error::SpiError(e.into()));
+        if let Some(cs_pin) = &self.cs_pin {
+            cs_pin.borrow_mut().set_high();
+        }
+        transferred
+    }
+
+    fn set_ce(&mut self, high: bool) {
+        if high {
+            self.ce_pin.set_high();
+        } else {
+            self.ce_pin.set_low();
+        }
+    }
+}
+
+impl RF24 {
+    pub fn new(config: &SpiConfig) -> Result<RF24, RF24Error> {
+        Ok(RF24::with_bus(Box::new(SpiBus::new(config)?)))
+    }
+}
@@ -0,0 +1,148 @@
+use crate::rf24::{RF24Error, Radio};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::thread::sleep;
+use std::time::Duration;
+
+/// Channels from 2400MHz to 2525MHz.
+pub const CHANNELS: usize = 126;
+
+/// Time the received power detector needs after listening starts.
+const RPD_SETTLE: Duration = Duration::from_micros(200);
+
+/// Width of the longest bar of the histogram.
+const HISTOGRAM_WIDTH: usize = 60;
+
+/// How busy each channel was during a scan.
+#[derive(Debug, Clone)]
+pub struct ChannelScan {
+    /// Number of times each channel was sampled.
+    pub sweeps: u32,
+    /// Number of samples where something was transmitting, per channel.
+    pub hits: [u32; CHANNELS],
+}
+
+impl ChannelScan {
+    /// Share of the samples where the channel was busy, from 0 to 1.
+    pub fn occupancy(&self, channel: usize) -> f32 {
+        if self.sweeps == 0 {
+            return 0.0;
+        }
+        self.hits[channel] as f32 / self.sweeps as f32
+    }
+
+    /// Every channel, from the quietest to the busiest, counting their neighbours too.
+    ///
+    /// At 250Kbps a transmission takes about 1MHz, so a quiet channel right next to a busy
+    /// one isn't that quiet. Ties go to the highest channel, above most of the WiFi channels.
+    pub fn ranking(&self) -> Vec<u8> {
+        let noise = |channel: usize| {
+            let first = channel.saturating_sub(1);
+            let last = (channel + 1).min(CHANNELS - 1);
+            self.hits[first..=last].iter().sum::<u32>() + self.hits[channel]
+        };
+
+        let mut channels: Vec<usize> = (0..CHANNELS).rev().collect();
+        channels.sort_by_key(|channel| noise(*channel));
+        channels.into_iter().map(|channel| channel as u8).collect()
+    }
+
+    /// The channel that was busy the least.
+    pub fn quietest_channel(&self) -> u8 {
+        self.ranking()[0]
+    }
+
+    /// One bar per channel, scaled to the busiest one.
+    pub fn histogram(&self) -> String {
+        let busiest = self.hits.iter().copied().max().unwrap_or(0).max(1);
+        let mut histogram = String::new();
+
+        for channel in 0..CHANNELS {
+            let width = (self.hits[channel] as usize * HISTOGRAM_WIDTH).div_ceil(busiest as usize);
+            let _ = writeln!(
+                histogram,
+                "{:3} {}MHz |{:<width$}| {:5.1}%",
+                channel,
+                2400 + channel,
+                "#".repeat(width),
+                self.occupancy(channel) * 100.0,
+                width = HISTOGRAM_WIDTH
+            );
+        }
+
+        histogram
+    }
+
+    pub fn write_csv<W: Write>(&self, mut output: W) -> io::Result<()> {
+        writeln!(output, "channel,frequency_mhz,samples,hits,occupancy")?;
+        for channel in 0..CHANNELS {
+            writeln!(
+                output,
+                "{},{},{},{},{:.4}",
+                channel,
+                2400 + channel,
+                self.sweeps,
+                self.hits[channel],
+                self.occupancy(channel)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Sweeps every channel `sweeps` times, listening for other transmitters with the received
+/// power detector.
+///
+/// The radio is left in standby, with CE low, on the last channel. The channel has to be set
+/// back before sending.
+pub fn scan(radio: &mut Radio, sweeps: u32) -> Result<ChannelScan, RF24Error> {
+    let mut scan = ChannelScan {
+        sweeps,
+        hits: [0; CHANNELS],
+    };
+
+    for _ in 0..sweeps {
+        for channel in 0..CHANNELS {
+            radio.set_channel(channel as u8)?;
+            radio.start_listening()?;
+            sleep(RPD_SETTLE);
+            if radio.received_power_detected()? {
+                scan.hits[channel] += 1;
+            }
+            radio.stop_listening()?;
+        }
+    }
+
+    Ok(scan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rf24::mock::{configured_radio, MockBus};
+
+    #[test]
+    fn finds_the_busy_channels() {
+        let bus = MockBus::new();
+        bus.chip.lock().unwrap().busy_channels = vec![10, 11, 125];
+        let mut radio = configured_radio(&bus);
+
+        let scan = scan(&mut radio, 2).unwrap();
+
+        assert_eq!(scan.hits[10], 2);
+        assert_eq!(scan.hits[11], 2);
+        assert_eq!(scan.hits[12], 0);
+        assert_eq!(scan.occupancy(125), 1.0);
+        assert_eq!(scan.quietest_channel(), 123);
+        assert_eq!(scan.ranking()[..3], [123, 122, 121]);
+        assert_eq!(scan.ranking().last(), Some(&10));
+
+        let mut csv = Vec::new();
+        scan.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), CHANNELS + 1);
+        assert_eq!(lines[11], "10,2410,2,2,1.0000");
+        assert_eq!(lines[13], "12,2412,2,0,0.0000");
+    }
+}
@@ -0,0 +1,294 @@
+use crate::controller::{Axis, Button, ControllerState, Input, InputError};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A single scripted change of the gamepad.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ScriptEvent {
+    Axis(Axis, i16),
+    Button(Button, bool),
+    /// Stops the application, like closing the SDL window would.
+    End,
+}
+
+/// A timeline of gamepad events, sorted by the time they happen.
+#[derive(Debug, Default)]
+pub struct Script {
+    events: Vec<(Duration, ScriptEvent)>,
+}
+
+impl Script {
+    /// Parses a script made of one event per line:
+    ///
+    /// ```text
+    /// # seconds  kind    name          value
+    /// 0.0        axis    lefty         -20000
+    /// 0.5        button  a             down
+    /// 1.5        button  a             up
+    /// 2.0        axis    lefty         0
+    /// 2.5        end
+    /// ```
+    ///
+    /// Axis and button names are the ones SDL uses in its game controller mappings.
+    /// Blank lines and lines starting with `#` are ignored.
+    pub fn parse(source: &str) -> Result<Script, String> {
+        let mut events = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let event = parse_line(line).map_err(|e| format!("line {}: {}", index + 1, e))?;
+            events.push(event);
+        }
+
+        // Stable, so events at the same time keep the order they were written in.
+        events.sort_by_key(|(at, _)| *at);
+
+        Ok(Script { events })
+    }
+
+    pub fn load(path: &Path) -> Result<Script, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Script::parse(&source)
+    }
+}
+
+fn parse_line(line: &str) -> Result<(Duration, ScriptEvent), String> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+
+    let seconds: f64 = fields[0]
+        .parse()
+        .map_err(|_| format!("invalid time '{}'", fields[0]))?;
+    if !seconds.is_finite() || seconds < 0.0 {
+        return Err(format!("invalid time '{}'", fields[0]));
+    }
+    let at = Duration::from_secs_f64(seconds);
+
+    let event = match fields[1..] {
+        ["axis", name, value] => {
+            let axis = parse_axis(name).ok_or_else(|| format!("unknown axis '{}'", name))?;
+            let value = value
+                .parse()
+                .map_err(|_| format!("invalid axis value '{}'", value))?;
+            ScriptEvent::Axis(axis, value)
+        }
+        ["button", name, state] => {
+            let button = parse_button(name).ok_or_else(|| format!("unknown button '{}'", name))?;
+            let pressed = match state {
+                "down" => true,
+                "up" => false,
+                _ => return Err(format!("invalid button state '{}'", state)),
+            };
+            ScriptEvent::Button(button, pressed)
+        }
+        ["end"] => ScriptEvent::End,
+        _ => return Err(format!("invalid event '{}'", line)),
+    };
+
+    Ok((at, event))
+}
+
+fn parse_axis(name: &str) -> Option<Axis> {
+    match name {
+        "leftx" => Some(Axis::LeftX),
+        "lefty" => Some(Axis::LeftY),
+        "rightx" => Some(Axis::RightX),
+        "righty" => Some(Axis::RightY),
+        "lefttrigger" => Some(Axis::TriggerLeft),
+        "righttrigger" => Some(Axis::TriggerRight),
+        _ => None,
+    }
+}
+
+fn parse_button(name: &str) -> Option<Button> {
+    match name {
+        "a" => Some(Button::A),
+        "b" => Some(Button::B),
+        "x" => Some(Button::X),
+        "y" => Some(Button::Y),
+        "back" => Some(Button::Back),
+        "start" => Some(Button::Start),
+        "leftshoulder" => Some(Button::LeftShoulder),
+        "rightshoulder" => Some(Button::RightShoulder),
+        _ => None,
+    }
+}
+
+/// Replays a `Script` into a `ControllerState`, as if a human was holding the gamepad.
+pub struct ScriptedInput {
+    state: ControllerState,
+    script: Script,
+    next: usize,
+    started: Instant,
+}
+
+impl ScriptedInput {
+    pub fn new(script: Script) -> Self {
+        ScriptedInput {
+            state: ControllerState::new(),
+            script,
+            next: 0,
+            started: Instant::now(),
+        }
+    }
+
+    /// Applies every event scheduled up to `elapsed` since the script started.
+    ///
+    /// Returns an error once the `end` event is reached.
+    pub fn update_at(&mut self, elapsed: Duration) -> Result<(), InputError> {
+        while let Some(&(at, event)) = self.script.events.get(self.next) {
+            if at > elapsed {
+                break;
+            }
+            self.next += 1;
+
+            match event {
+                ScriptEvent::Axis(axis, value) => self.state.update_axis(axis, value),
+                ScriptEvent::Button(button, pressed) => self.state.update_button(button, pressed),
+                ScriptEvent::End => return Err(InputError::ScriptFinished),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Input for ScriptedInput {
+    fn get_state(&self) -> &ControllerState {
+        &self.state
+    }
+
+    fn update_state(&mut self) -> Result<(), InputError> {
+        self.update_at(self.started.elapsed())
+    }
+}
+
+pub fn init(path: &Path) -> Result<ScriptedInput, InputError> {
+    Ok(ScriptedInput::new(
+        Script::load(path).map_err(InputError::Script)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::CONTROL_MARKER;
+    use crate::rf24::mock::{configured_radio, MockBus};
+
+    const SCRIPT: &str = "
+        # Drive forward, honk, then stop
+        0.0 axis lefty -20000
+        0.0 axis righttrigger 32767
+        0.5 button a down
+        1.0 button a up
+        1.0 axis lefty 100
+        2.0 end
+    ";
+
+    #[test]
+    fn parses_events_in_time_order() {
+        let script = Script::parse("1.0 button start down\n0.5 axis leftx 1234\n2 end").unwrap();
+
+        assert_eq!(
+            script.events,
+            vec![
+                (
+                    Duration::from_millis(500),
+                    ScriptEvent::Axis(Axis::LeftX, 1234)
+                ),
+                (
+                    Duration::from_secs(1),
+                    ScriptEvent::Button(Button::Start, true)
+                ),
+                (Duration::from_secs(2), ScriptEvent::End),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_invalid_events() {
+        let error = Script::parse("0.0 axis lefty 0\n\n0.5 button z down").unwrap_err();
+        assert_eq!(error, "line 3: unknown button 'z'");
+
+        let error = Script::parse("soon end").unwrap_err();
+        assert_eq!(error, "line 1: invalid time 'soon'");
+    }
+
+    #[test]
+    fn transmits_the_scripted_frames() {
+        let bus = MockBus::new();
+        let mut radio = configured_radio(&bus);
+        let mut input = ScriptedInput::new(Script::parse(SCRIPT).unwrap());
+
+        for millis in [0, 500, 1000] {
+            input.update_at(Duration::from_millis(millis)).unwrap();
+            radio.send(&input.get_state().to_bytes()).unwrap();
+        }
+
+        let throttle = (-20000i16).to_be_bytes();
+        assert_eq!(
+            bus.transmitted(),
+            vec![
+                vec![
+                    0x00,
+                    0,
+                    0,
+                    0x7F,
+                    0xFF,
+                    0,
+                    0,
+                    throttle[0],
+                    throttle[1],
+                    0,
+                    0,
+                    0,
+                    0
+                ],
+                vec![
+                    0x01,
+                    0,
+                    0,
+                    0x7F,
+                    0xFF,
+                    0,
+                    0,
+                    throttle[0],
+                    throttle[1],
+                    0,
+                    0,
+                    0,
+                    0
+                ],
+                // The stick is back inside the deadzone.
+                vec![0x00, 0, 0, 0x7F, 0xFF, 0, 0, 0, 0, 0, 0, 0, 0],
+            ]
+        );
+    }
+
+    #[test]
+    fn negative_triggers_never_make_the_control_marker() {
+        let script = Script::parse("0.0 axis lefttrigger -1\n0.0 axis righttrigger -32768");
+        let mut input = ScriptedInput::new(script.unwrap());
+        input.update_at(Duration::ZERO).unwrap();
+
+        let state = input.get_state();
+        assert_eq!((state.l2, state.r2), (0, 0));
+        assert_ne!(state.to_bytes()[1..3], CONTROL_MARKER);
+    }
+
+    #[test]
+    fn stops_at_the_end_of_the_script() {
+        let mut input = ScriptedInput::new(Script::parse(SCRIPT).unwrap());
+
+        assert!(input.update_at(Duration::from_millis(1999)).is_ok());
+        assert!(matches!(
+            input.update_at(Duration::from_secs(2)),
+            Err(InputError::ScriptFinished)
+        ));
+    }
+}
@@ -0,0 +1,217 @@
+//! Gamepads read through SDL, built with the `sdl2` feature.
+
+use crate::controller::{Axis, Button, ControllerState, Input, InputError};
+use crate::tilt::TiltSteering;
+use sdl2::{
+    controller::{self, GameController},
+    event::Event,
+    sensor::SensorType,
+    EventPump, GameControllerSubsystem,
+};
+use std::time::Duration;
+
+struct Sdl {
+    /// Keeps SDL initialized while the subsystems are in use.
+    _context: sdl2::Sdl,
+    controller_subsystem: GameControllerSubsystem,
+    event_pump: EventPump,
+    controller: Option<GameController>,
+}
+
+pub struct Controller {
+    state: ControllerState,
+    sdl: Sdl,
+    tilt: Option<TiltSteering>,
+    /// SDL timestamp of the last gyroscope reading, in milliseconds.
+    last_gyro: Option<u32>,
+}
+
+impl Controller {
+    /// Steers with the gamepad motion sensors instead of the left stick.
+    ///
+    /// Pressing the left stick takes the current position as straight ahead.
+    pub fn enable_tilt_steering(&mut self) {
+        self.tilt = Some(TiltSteering::new());
+    }
+}
+
+/// Turns on the gyroscope and accelerometer of the gamepad.
+///
+/// Returns false if the gamepad doesn't have both.
+fn enable_motion_sensors(controller: &GameController) -> bool {
+    let sensors = [SensorType::Gyroscope, SensorType::Accelerometer];
+
+    sensors.iter().all(|sensor| {
+        controller.has_sensor(*sensor) && controller.sensor_set_enabled(*sensor, true).is_ok()
+    })
+}
+
+impl Input for Controller {
+    fn get_state(&self) -> &ControllerState {
+        &self.state
+    }
+
+    /// Listen for the SDL events and updates the controller state when a controller event is received.
+    ///
+    /// Returns an error if the user has quit the application.
+    fn update_state(&mut self) -> Result<(), InputError> {
+        let Sdl {
+            controller_subsystem,
+            event_pump,
+            controller,
+            ..
+        } = &mut self.sdl;
+
+        let controller_state = &mut self.state;
+        let tilt = &mut self.tilt;
+        let last_gyro = &mut self.last_gyro;
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. } => {
+                    return Err(InputError::Quit);
+                }
+                Event::ControllerDeviceAdded { which, .. } => {
+                    // A gamepad that can't be opened is ignored, the robot is stopped by the
+                    // link timeout rather than by crashing.
+                    if controller_subsystem.num_joysticks().unwrap_or(0) > 1 {
+                        println!(
+                            "More than one controller was attached. Only one can be used at a time."
+                        );
+                    } else {
+                        match controller_subsystem.open(which) {
+                            Ok(new_controller) => {
+                                println!("Controller attached: {}", new_controller.name());
+                                if tilt.is_some() && !enable_motion_sensors(&new_controller) {
+                                    println!("The controller has no motion sensors to steer with.");
+                                }
+                                *controller = Some(new_controller);
+                                // The readings of the previous gamepad are no reference
+                                *last_gyro = None;
+                            }
+                            Err(e) => println!("Could not open the controller: {}", e),
+                        }
+                    }
+                }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    *controller = None;
+                    *last_gyro = None;
+                    println!("Joystick detached: {}", which);
+                }
+                Event::ControllerAxisMotion {
+                    axis: controller::Axis::LeftX,
+                    ..
+                } if tilt.is_some() => {}
+                Event::ControllerAxisMotion { axis, value, .. } => {
+                    controller_state.update_axis(map_axis(axis), value);
+                }
+                Event::ControllerSensorUpdated {
+                    timestamp,
+                    sensor,
+                    data,
+                    ..
+                } => {
+                    if let Some(tilt) = tilt {
+                        match sensor {
+                            SensorType::Accelerometer => tilt.update_accelerometer(data),
+                            // The readings come in batches between two polls, each is integrated
+                            // over the time since the previous one rather than since the poll
+                            SensorType::Gyroscope => {
+                                if let Some(last) = *last_gyro {
+                                    let elapsed = timestamp.wrapping_sub(last);
+                                    tilt.update_gyroscope(
+                                        data,
+                                        Duration::from_millis(elapsed as u64),
+                                    );
+                                }
+                                *last_gyro = Some(timestamp);
+                            }
+                            _ => {}
+                        }
+                        controller_state.set_yaw(tilt.yaw());
+                    }
+                }
+                Event::ControllerButtonDown {
+                    button: controller::Button::LeftStick,
+                    ..
+                } => {
+                    if let Some(tilt) = tilt {
+                        tilt.recenter();
+                    }
+                }
+                Event::ControllerButtonDown { button, .. } => {
+                    if let Some(button) = map_button(button) {
+                        controller_state.update_button(button, true);
+                    }
+                }
+                Event::ControllerButtonUp { button, .. } => {
+                    if let Some(button) = map_button(button) {
+                        controller_state.update_button(button, false);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn rumble(&mut self, low_frequency: u16, high_frequency: u16, duration: Duration) {
+        if let Some(controller) = &mut self.sdl.controller {
+            // Gamepads without rumble return an error, there is nothing to do about it.
+            let _ =
+                controller.set_rumble(low_frequency, high_frequency, duration.as_millis() as u32);
+        }
+    }
+
+    fn set_led(&mut self, red: u8, green: u8, blue: u8) {
+        if let Some(controller) = &mut self.sdl.controller {
+            let _ = controller.set_led(red, green, blue);
+        }
+    }
+}
+
+fn map_button(button: controller::Button) -> Option<Button> {
+    match button {
+        controller::Button::A => Some(Button::A),
+        controller::Button::B => Some(Button::B),
+        controller::Button::X => Some(Button::X),
+        controller::Button::Y => Some(Button::Y),
+        controller::Button::Back => Some(Button::Back),
+        controller::Button::Start => Some(Button::Start),
+        controller::Button::LeftShoulder => Some(Button::LeftShoulder),
+        controller::Button::RightShoulder => Some(Button::RightShoulder),
+        _ => None,
+    }
+}
+
+fn map_axis(axis: controller::Axis) -> Axis {
+    match axis {
+        controller::Axis::LeftX => Axis::LeftX,
+        controller::Axis::LeftY => Axis::LeftY,
+        controller::Axis::RightX => Axis::RightX,
+        controller::Axis::RightY => Axis::RightY,
+        controller::Axis::TriggerLeft => Axis::TriggerLeft,
+        controller::Axis::TriggerRight => Axis::TriggerRight,
+    }
+}
+
+pub fn init() -> Result<Controller, InputError> {
+    let sdl_context = sdl2::init().map_err(InputError::Sdl)?;
+    let controller_subsystem = sdl_context
+        .game_controller()
+        .map_err(|e| InputError::Sdl(e.to_string()))?;
+    let event_pump = sdl_context.event_pump().map_err(InputError::Sdl)?;
+
+    Ok(Controller {
+        state: ControllerState::new(),
+        sdl: Sdl {
+            _context: sdl_context,
+            controller_subsystem,
+            event_pump,
+            controller: None,
+        },
+        tilt: None,
+        last_gyro: None,
+    })
+}
@@ -0,0 +1,299 @@
+//! An nRF24 on a microcontroller plugged in over USB serial, for hosts without SPI.
+//!
+//! Both ways, the messages are framed the same:
+//!
+//! |Field|Size|Description|
+//! |---|:---:|:---|
+//! |Sync|1|`0xA5`|
+//! |Length|1|Size of the sequence number and the body|
+//! |Sequence|1|Number of the frame, echoed in the answer|
+//! |Body|Length - 1|The payload to send, or the delivery in the answer|
+//! |Checksum|1|CRC-8 of the length, sequence and body, polynomial 0x07|
+//!
+//! The host sends a payload, the dongle sends it with its radio and answers with the delivery,
+//! encoded as for the gateway (see `transport`), so the telemetry comes back in the ACK payload.
+//! Bytes that don't make a valid frame are skipped up to the next sync byte.
+//!
+//! The firmware of the dongle, for an Arduino Nano, is in `dongle` at the root of the repository.
+
+use crate::error::AppError;
+use crate::rf24::Delivery;
+use crate::transport::{decode_delivery, Transport};
+use nix::poll::{poll, PollFd, PollFlags, PollTimeout};
+use nix::sys::termios::{self, BaudRate, FlushArg, SetArg, SpecialCharacterIndices};
+use std::fs::File;
+use std::io::{self, ErrorKind, Read, Write};
+use std::os::fd::AsFd;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const SYNC: u8 = 0xA5;
+
+/// Time to wait for the dongle to answer, which covers the retransmissions of its radio.
+const REPLY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Longest sequence number and body: an ACKed delivery with a full ACK payload.
+const MAX_LENGTH: usize = 1 + 2 + 32;
+
+/// Speeds the dongle can be set to, in bauds.
+pub const BAUD_RATES: [(u32, BaudRate); 7] = [
+    (9600, BaudRate::B9600),
+    (19200, BaudRate::B19200),
+    (38400, BaudRate::B38400),
+    (57600, BaudRate::B57600),
+    (115200, BaudRate::B115200),
+    (230400, BaudRate::B230400),
+    (1000000, BaudRate::B1000000),
+];
+
+/// CRC-8 with the polynomial 0x07, simple enough for the dongle to compute on the fly.
+pub fn crc8(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0, |crc, byte| {
+        (0..8).fold(crc ^ byte, |crc, _| {
+            if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// A frame holding `body`.
+pub fn encode_frame(sequence: u8, body: &[u8]) -> Vec<u8> {
+    let mut frame = vec![SYNC, body.len() as u8 + 1, sequence];
+    frame.extend_from_slice(body);
+    frame.push(crc8(&frame[1..]));
+    frame
+}
+
+/// Takes the first valid frame out of `buffer`, as its sequence number and body.
+///
+/// The bytes before it are dropped, a frame that's still incomplete is left for later. A sync
+/// byte whose frame doesn't check out is skipped, and so is one still waiting for its bytes when
+/// a complete frame follows it: it was part of something else, and waiting for it would hold the
+/// answer back until the reply timeout.
+pub fn decode_frame(buffer: &mut Vec<u8>) -> Option<(u8, Vec<u8>)> {
+    let mut incomplete = None;
+
+    for start in 0..buffer.len() {
+        if buffer[start] != SYNC {
+            continue;
+        }
+        let Some(&length) = buffer.get(start + 1) else {
+            incomplete = incomplete.or(Some(start));
+            continue;
+        };
+        let length = length as usize;
+        if length == 0 || length > MAX_LENGTH {
+            continue;
+        }
+        let Some(frame) = buffer.get(start..start + length + 3) else {
+            incomplete = incomplete.or(Some(start));
+            continue;
+        };
+        if crc8(&frame[1..length + 2]) != frame[length + 2] {
+            continue;
+        }
+
+        let decoded = (frame[2], frame[3..length + 2].to_vec());
+        buffer.drain(..start + length + 3);
+        return Some(decoded);
+    }
+
+    buffer.drain(..incomplete.unwrap_or(buffer.len()));
+    None
+}
+
+/// Sends the payloads through a dongle on a serial port, see the module documentation.
+pub struct SerialTransport {
+    port: File,
+    path: PathBuf,
+    sequence: u8,
+    received: Vec<u8>,
+}
+
+impl SerialTransport {
+    /// Opens the serial port at `path` in raw mode, at `baud_rate`.
+    ///
+    /// Most Arduinos reset when the port is opened, the first frames are lost while they boot.
+    pub fn open(path: &Path, baud_rate: BaudRate) -> Result<SerialTransport, AppError> {
+        let port = File::options()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(AppError::file(path))?;
+        configure(&port, baud_rate)
+            .map_err(io::Error::from)
+            .map_err(AppError::file(path))?;
+
+        Ok(SerialTransport {
+            port,
+            path: path.to_path_buf(),
+            sequence: 0,
+            received: Vec::new(),
+        })
+    }
+
+    /// Waits for bytes from the dongle until `deadline`, returning false once it's passed.
+    fn receive(&mut self, deadline: Instant) -> io::Result<bool> {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+
+        let timeout = PollTimeout::try_from(remaining).unwrap_or(PollTimeout::MAX);
+        let mut fds = [PollFd::new(self.port.as_fd(), PollFlags::POLLIN)];
+        if poll(&mut fds, timeout)? == 0 {
+            return Ok(false);
+        }
+
+        let mut bytes = [0u8; 64];
+        let length = self.port.read(&mut bytes)?;
+        if length == 0 {
+            return Err(io::Error::new(
+                ErrorKind::UnexpectedEof,
+                "the dongle went away",
+            ));
+        }
+        self.received.extend_from_slice(&bytes[..length]);
+        Ok(true)
+    }
+}
+
+impl Transport for SerialTransport {
+    /// Sends a payload through the dongle and waits for its delivery.
+    ///
+    /// Without an answer within `REPLY_TIMEOUT` the payload is `Lost`. Late answers to earlier
+    /// payloads are skipped. The port failing, usually because the dongle was unplugged, stops
+    /// the controller.
+    fn send(&mut self, payload: &[u8]) -> Result<Delivery, AppError> {
+        self.sequence = self.sequence.wrapping_add(1);
+        self.port
+            .write_all(&encode_frame(self.sequence, payload))
+            .map_err(AppError::file(&self.path))?;
+
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        loop {
+            while let Some((sequence, body)) = decode_frame(&mut self.received) {
+                if sequence == self.sequence {
+                    if let Some(delivery) = decode_delivery(&body) {
+                        return Ok(delivery);
+                    }
+                }
+            }
+
+            match self.receive(deadline) {
+                Ok(true) => {}
+                Ok(false) => return Ok(Delivery::Lost),
+                Err(e) => return Err(AppError::file(&self.path)(e)),
+            }
+        }
+    }
+}
+
+/// Raw mode, so no byte gets interpreted, with reads that return at once.
+fn configure(port: &File, baud_rate: BaudRate) -> nix::Result<()> {
+    let mut settings = termios::tcgetattr(port)?;
+    termios::cfmakeraw(&mut settings);
+    termios::cfsetspeed(&mut settings, baud_rate)?;
+    settings.control_chars[SpecialCharacterIndices::VMIN as usize] = 0;
+    settings.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+    termios::tcsetattr(port, SetArg::TCSANOW, &settings)?;
+    termios::tcflush(port, FlushArg::TCIOFLUSH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::encode_delivery;
+    use nix::pty::openpty;
+    use nix::unistd::ttyname;
+    use std::thread;
+
+    /// Answers the frames like a dongle whose robot attaches `ack_payload` to the first ACK,
+    /// with garbage before each answer.
+    fn fake_dongle(
+        mut port: File,
+        frames: usize,
+        ack_payload: Vec<u8>,
+    ) -> thread::JoinHandle<File> {
+        thread::spawn(move || {
+            let mut received = Vec::new();
+            let mut ack_payload = Some(ack_payload);
+            for _ in 0..frames {
+                let (sequence, _) = loop {
+                    if let Some(frame) = decode_frame(&mut received) {
+                        break frame;
+                    }
+                    let mut bytes = [0u8; 64];
+                    let length = port.read(&mut bytes).unwrap();
+                    received.extend_from_slice(&bytes[..length]);
+                };
+                let delivery = Delivery::Acked {
+                    retries: 1,
+                    payload: ack_payload.take(),
+                };
+                let mut answer = vec![0x00, SYNC, 0x42];
+                answer.extend(encode_frame(sequence, &encode_delivery(&delivery)));
+                port.write_all(&answer).unwrap();
+            }
+            port
+        })
+    }
+
+    #[test]
+    fn sends_through_a_dongle() {
+        let pty = openpty(None, None).unwrap();
+        let path = ttyname(&pty.slave).unwrap();
+        let mut transport = SerialTransport::open(&path, BaudRate::B115200).unwrap();
+        let dongle = fake_dongle(File::from(pty.master), 2, vec![1, 0x1F, 0x40, 0, 200]);
+
+        assert_eq!(
+            transport.send(&[7; 13]).unwrap(),
+            Delivery::Acked {
+                retries: 1,
+                payload: Some(vec![1, 0x1F, 0x40, 0, 200]),
+            }
+        );
+        assert_eq!(
+            transport.send(&[8; 13]).unwrap(),
+            Delivery::Acked {
+                retries: 1,
+                payload: None,
+            }
+        );
+        let _port = dongle.join().unwrap();
+
+        // The dongle doesn't answer anymore, but the port is still open
+        assert_eq!(transport.send(&[9; 13]).unwrap(), Delivery::Lost);
+    }
+
+    #[test]
+    fn skips_what_isnt_a_frame() {
+        let mut buffer = vec![0x13, SYNC, SYNC, 0x02];
+        buffer.extend(encode_frame(4, &[0x02]));
+        buffer.extend(encode_frame(5, &[]));
+        buffer.push(SYNC);
+
+        assert_eq!(decode_frame(&mut buffer), Some((4, vec![0x02])));
+        assert_eq!(decode_frame(&mut buffer), Some((5, vec![])));
+        assert_eq!(decode_frame(&mut buffer), None);
+        assert_eq!(buffer, vec![SYNC]);
+    }
+
+    #[test]
+    fn doesnt_wait_for_a_frame_that_isnt_one() {
+        // A stray sync byte announcing a long frame, followed by a short answer
+        let mut buffer = vec![SYNC, 20];
+        buffer.extend(encode_frame(7, &[0x02]));
+
+        assert_eq!(decode_frame(&mut buffer), Some((7, vec![0x02])));
+        assert!(buffer.is_empty());
+
+        // Without a complete frame after it, it may still be one
+        let mut buffer = vec![0x13, SYNC, 3, 7];
+        assert_eq!(decode_frame(&mut buffer), None);
+        assert_eq!(buffer, vec![SYNC, 3, 7]);
+    }
+}
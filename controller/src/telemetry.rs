@@ -0,0 +1,72 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Size of the telemetry the robot attaches to the ACKs.
+pub const TELEMETRY_SIZE: usize = 5;
+
+/// The state machine of the robot firmware.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+#[serde(into = "String")]
+pub enum RobotMode {
+    Initializing,
+    Running,
+    /// Steering calibration, toggled with Select.
+    Setup,
+    Disconnected,
+    Unknown(u8),
+}
+
+impl From<u8> for RobotMode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => RobotMode::Initializing,
+            1 => RobotMode::Running,
+            2 => RobotMode::Setup,
+            3 => RobotMode::Disconnected,
+            _ => RobotMode::Unknown(value),
+        }
+    }
+}
+
+impl fmt::Display for RobotMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RobotMode::Unknown(mode) => write!(f, "Unknown({})", mode),
+            mode => write!(f, "{:?}", mode),
+        }
+    }
+}
+
+impl From<RobotMode> for String {
+    fn from(mode: RobotMode) -> String {
+        mode.to_string()
+    }
+}
+
+/// Robot status, sent back inside the ACK of each controller state frame.
+///
+/// |Field|Payload byte index|Data type|Description|
+/// |---|:---:|:---|:---|
+/// |Mode|0|unsigned int8|`RobotMode`|
+/// |Voltage|1-2|unsigned int16|Battery voltage in millivolts, MSB first|
+/// |Current|3-4|signed int16|Motor current in milliamps, MSB first|
+#[derive(Debug, Copy, Clone, PartialEq, Serialize)]
+pub struct Telemetry {
+    pub mode: RobotMode,
+    pub voltage: u16,
+    pub current: i16,
+}
+
+impl Telemetry {
+    pub fn from_bytes(bytes: &[u8]) -> Option<Telemetry> {
+        if bytes.len() != TELEMETRY_SIZE {
+            return None;
+        }
+
+        Some(Telemetry {
+            mode: RobotMode::from(bytes[0]),
+            voltage: u16::from_be_bytes([bytes[1], bytes[2]]),
+            current: i16::from_be_bytes([bytes[3], bytes[4]]),
+        })
+    }
+}
@@ -0,0 +1,145 @@
+use std::f32::consts::PI;
+use std::time::Duration;
+
+/// Weight of the integrated gyroscope in the complementary filter. The remaining weight goes
+/// to the accelerometer, which is noisy but doesn't drift.
+const GYRO_WEIGHT: f32 = 0.98;
+
+/// Tilt around the center that is still considered straight, in degrees.
+const TILT_DEADZONE: f32 = 4.0;
+
+/// Tilt that turns the wheels all the way, in degrees.
+const TILT_RANGE: f32 = 45.0;
+
+/// Steers the robot by tilting the gamepad like a steering wheel.
+///
+/// The gyroscope and accelerometer are fused into the angle the gamepad is rotated around
+/// the axis pointing at the driver, positive to the right.
+#[derive(Debug)]
+pub struct TiltSteering {
+    angle: f32,
+    center: f32,
+    accel_angle: Option<f32>,
+}
+
+impl TiltSteering {
+    pub fn new() -> Self {
+        TiltSteering {
+            angle: 0.0,
+            center: 0.0,
+            accel_angle: None,
+        }
+    }
+
+    /// Feeds an accelerometer reading, in m/s², along the SDL sensor axes.
+    pub fn update_accelerometer(&mut self, data: [f32; 3]) {
+        let [x, y, _] = data;
+        // Rolling the gamepad to the right moves gravity from +y to -x.
+        let angle = (-x).atan2(y) * 180.0 / PI;
+
+        if self.accel_angle.is_none() {
+            self.angle = angle;
+        }
+        self.accel_angle = Some(angle);
+    }
+
+    /// Feeds a gyroscope reading, in rad/s, taken `elapsed` after the previous one.
+    pub fn update_gyroscope(&mut self, data: [f32; 3], elapsed: Duration) {
+        let Some(accel_angle) = self.accel_angle else {
+            return;
+        };
+
+        // SDL rolls anti-clockwise around +z, which points at the driver, so right is negative.
+        let rate = -data[2] * 180.0 / PI;
+        let gyro_angle = self.angle + rate * elapsed.as_secs_f32();
+
+        self.angle = GYRO_WEIGHT * gyro_angle + (1.0 - GYRO_WEIGHT) * accel_angle;
+    }
+
+    /// Takes the current position as straight ahead.
+    pub fn recenter(&mut self) {
+        self.center = self.angle;
+    }
+
+    /// Tilt from the center, in degrees.
+    pub fn angle(&self) -> f32 {
+        self.angle - self.center
+    }
+
+    /// The tilt mapped onto the range of the yaw stick.
+    pub fn yaw(&self) -> i16 {
+        let angle = self.angle();
+        if angle.abs() <= TILT_DEADZONE {
+            return 0;
+        }
+
+        let travel = (angle.abs() - TILT_DEADZONE) / (TILT_RANGE - TILT_DEADZONE);
+        let yaw = travel.min(1.0) * 32_767.0;
+
+        (angle.signum() * yaw) as i16
+    }
+}
+
+impl Default for TiltSteering {
+    fn default() -> Self {
+        TiltSteering::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAVITY: f32 = 9.81;
+
+    fn tilted(degrees: f32) -> [f32; 3] {
+        let radians = degrees * PI / 180.0;
+        [-GRAVITY * radians.sin(), GRAVITY * radians.cos(), 0.0]
+    }
+
+    #[test]
+    fn maps_the_tilt_onto_the_yaw() {
+        let mut tilt = TiltSteering::new();
+
+        tilt.update_accelerometer(tilted(0.0));
+        assert_eq!(tilt.yaw(), 0);
+
+        let mut tilt = TiltSteering::new();
+        tilt.update_accelerometer(tilted(TILT_DEADZONE - 1.0));
+        assert_eq!(tilt.yaw(), 0);
+
+        let mut tilt = TiltSteering::new();
+        tilt.update_accelerometer(tilted(60.0));
+        assert_eq!(tilt.yaw(), 32_767);
+
+        let mut tilt = TiltSteering::new();
+        tilt.update_accelerometer(tilted(-(TILT_DEADZONE + TILT_RANGE) / 2.0));
+        assert!((tilt.yaw() as i32 + 32_767 / 2).abs() < 400);
+    }
+
+    #[test]
+    fn follows_the_gyroscope_between_accelerometer_readings() {
+        let mut tilt = TiltSteering::new();
+        tilt.update_accelerometer(tilted(0.0));
+
+        // Rolling right at 90°/s for 100ms
+        let rate = -PI / 2.0;
+        tilt.update_gyroscope([0.0, 0.0, rate], Duration::from_millis(100));
+
+        assert!((tilt.angle() - 9.0 * GYRO_WEIGHT).abs() < 0.01);
+    }
+
+    #[test]
+    fn recenters_on_the_current_position() {
+        let mut tilt = TiltSteering::new();
+        tilt.update_accelerometer(tilted(20.0));
+        assert!(tilt.yaw() > 0);
+
+        tilt.recenter();
+        assert_eq!(tilt.yaw(), 0);
+
+        tilt.update_accelerometer(tilted(20.0));
+        tilt.update_gyroscope([0.0, 0.0, 0.0], Duration::from_millis(10));
+        assert_eq!(tilt.yaw(), 0);
+    }
+}
@@ -0,0 +1,257 @@
+//! Ways of getting the frames to the robot: the local radio, or a gateway over UDP.
+//!
+//! A frame sent to a gateway is a datagram holding a sequence number, big-endian on 2 bytes,
+//! followed by the payload. The gateway sends it with its radio and answers with a datagram
+//! holding the same sequence number, followed by the delivery:
+//!
+//! |Delivery|Bytes|
+//! |---|:---|
+//! |Unacknowledged|`0x00`|
+//! |Acked|`0x01`, retries, ACK payload if any|
+//! |Lost|`0x02`|
+//!
+//! The gateway doesn't answer when its radio fails, the frame is then counted as lost.
+
+use crate::error::AppError;
+use crate::rf24::{Delivery, Radio};
+use std::io::{self, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Default UDP port of the gateway.
+pub const DEFAULT_PORT: u16 = 7870;
+
+/// Time to wait for the gateway to answer, which covers the retransmissions of its radio.
+const REPLY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Longest payload the radio can send.
+const MAX_PAYLOAD_SIZE: usize = 32;
+
+const UNACKNOWLEDGED: u8 = 0x00;
+const ACKED: u8 = 0x01;
+const LOST: u8 = 0x02;
+
+/// Something that takes the frames to the robot.
+pub trait Transport {
+    fn send(&mut self, payload: &[u8]) -> Result<Delivery, AppError>;
+
+    /// The radio the frames go out of, when it's attached to this host.
+    ///
+    /// The health checks, power states and channel changes can only be done on a local radio.
+    fn radio(&mut self) -> Option<&mut Radio> {
+        None
+    }
+}
+
+impl Transport for Radio {
+    fn send(&mut self, payload: &[u8]) -> Result<Delivery, AppError> {
+        Ok(Radio::send(self, payload)?)
+    }
+
+    fn radio(&mut self) -> Option<&mut Radio> {
+        Some(self)
+    }
+}
+
+/// Sends the frames to a gateway that has the radio, see `Gateway`.
+pub struct UdpTransport {
+    socket: UdpSocket,
+    sequence: u16,
+}
+
+impl UdpTransport {
+    /// Prepares to send to the gateway at `address`. Nothing is sent until the first frame.
+    pub fn connect(address: impl ToSocketAddrs) -> io::Result<UdpTransport> {
+        let gateway = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "no address for the gateway"))?;
+        let local: SocketAddr = if gateway.is_ipv4() {
+            ([0, 0, 0, 0], 0).into()
+        } else {
+            ([0u16; 8], 0).into()
+        };
+
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(gateway)?;
+
+        Ok(UdpTransport {
+            socket,
+            sequence: 0,
+        })
+    }
+}
+
+impl Transport for UdpTransport {
+    /// Sends a frame to the gateway and waits for its delivery.
+    ///
+    /// Without an answer within `REPLY_TIMEOUT` the frame is `Lost`. Answers to earlier frames
+    /// that arrive late are skipped.
+    fn send(&mut self, payload: &[u8]) -> Result<Delivery, AppError> {
+        self.sequence = self.sequence.wrapping_add(1);
+        let datagram = [&self.sequence.to_be_bytes()[..], payload].concat();
+        self.socket.send(&datagram).map_err(AppError::Network)?;
+
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        let mut buffer = [0u8; 3 + MAX_PAYLOAD_SIZE];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(Delivery::Lost);
+            }
+            self.socket
+                .set_read_timeout(Some(remaining))
+                .map_err(AppError::Network)?;
+
+            let length = match self.socket.recv(&mut buffer) {
+                Ok(length) => length,
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    return Ok(Delivery::Lost)
+                }
+                Err(e) => return Err(AppError::Network(e)),
+            };
+
+            if length >= 2 && buffer[..2] == self.sequence.to_be_bytes() {
+                if let Some(delivery) = decode_delivery(&buffer[2..length]) {
+                    return Ok(delivery);
+                }
+            }
+        }
+    }
+}
+
+/// Forwards the frames received over UDP to the local radio, and answers with their delivery.
+///
+/// It runs next to the robot, so the driver can be anywhere on the network. Frames from every
+/// sender are forwarded, only one controller should use a gateway at a time.
+pub struct Gateway {
+    socket: UdpSocket,
+}
+
+impl Gateway {
+    pub fn bind(address: impl ToSocketAddrs) -> io::Result<Gateway> {
+        Ok(Gateway {
+            socket: UdpSocket::bind(address)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// Waits for the next frame and sends it with `radio`.
+    ///
+    /// Datagrams that can't be a frame are dropped. Radio errors are returned without
+    /// answering, for the caller to decide whether to carry on.
+    pub fn forward(&self, radio: &mut dyn Transport) -> Result<(), AppError> {
+        let mut buffer = [0u8; 2 + MAX_PAYLOAD_SIZE + 1];
+        let (length, sender) = self
+            .socket
+            .recv_from(&mut buffer)
+            .map_err(AppError::Network)?;
+        if !(3..=2 + MAX_PAYLOAD_SIZE).contains(&length) {
+            return Ok(());
+        }
+
+        let delivery = radio.send(&buffer[2..length])?;
+
+        let reply = [&buffer[..2], &encode_delivery(&delivery)].concat();
+        self.socket
+            .send_to(&reply, sender)
+            .map_err(AppError::Network)?;
+
+        Ok(())
+    }
+}
+
+/// The delivery as sent back by a gateway or a serial dongle, see the table above.
+pub fn encode_delivery(delivery: &Delivery) -> Vec<u8> {
+    match delivery {
+        Delivery::Unacknowledged => vec![UNACKNOWLEDGED],
+        Delivery::Acked { retries, payload } => {
+            let mut bytes = vec![ACKED, *retries];
+            bytes.extend(payload.iter().flatten());
+            bytes
+        }
+        Delivery::Lost => vec![LOST],
+    }
+}
+
+/// The delivery in an answer of a gateway or a serial dongle, `None` if it isn't one.
+pub fn decode_delivery(bytes: &[u8]) -> Option<Delivery> {
+    match bytes {
+        [UNACKNOWLEDGED] => Some(Delivery::Unacknowledged),
+        // The radio never receives an empty ACK payload
+        [ACKED, retries, payload @ ..] => Some(Delivery::Acked {
+            retries: *retries,
+            payload: (!payload.is_empty()).then(|| payload.to_vec()),
+        }),
+        [LOST] => Some(Delivery::Lost),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rf24::mock::{configured_radio, MockBus};
+    use std::thread;
+
+    #[test]
+    fn sends_frames_through_a_gateway() {
+        let bus = MockBus::new();
+        bus.chip
+            .lock()
+            .unwrap()
+            .ack_payloads
+            .push_back(vec![1, 2, 3]);
+        let mut radio = configured_radio(&bus);
+        radio.set_ack_payloads(true).unwrap();
+        radio.configure().unwrap();
+
+        let gateway = Gateway::bind("127.0.0.1:0").unwrap();
+        let mut transport = UdpTransport::connect(gateway.local_addr().unwrap()).unwrap();
+        let forwarding = thread::spawn(move || {
+            for _ in 0..2 {
+                gateway.forward(&mut radio).unwrap();
+            }
+            gateway
+        });
+
+        assert_eq!(
+            transport.send(&[7; 13]).unwrap(),
+            Delivery::Acked {
+                retries: 0,
+                payload: Some(vec![1, 2, 3]),
+            }
+        );
+        assert_eq!(
+            transport.send(&[8; 13]).unwrap(),
+            Delivery::Acked {
+                retries: 0,
+                payload: None,
+            }
+        );
+        let _gateway = forwarding.join().unwrap();
+        assert_eq!(bus.transmitted(), vec![vec![7; 13], vec![8; 13]]);
+
+        // The gateway is still there, but doesn't forward anymore
+        assert_eq!(transport.send(&[9; 13]).unwrap(), Delivery::Lost);
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let deliveries = [
+            Delivery::Unacknowledged,
+            Delivery::Lost,
+            Delivery::Acked {
+                retries: 3,
+                payload: Some(vec![1, 0x1F, 0x40, 0, 200]),
+            },
+        ];
+        for delivery in deliveries {
+            assert_eq!(decode_delivery(&encode_delivery(&delivery)), Some(delivery));
+        }
+        assert_eq!(decode_delivery(&[0x03]), None);
+    }
+}
@@ -0,0 +1,369 @@
+//! Browsers and phones as gamepads.
+//!
+//! A small web page reads the gamepad of the browser, or draws touch joysticks, and streams the
+//! controller state over a WebSocket on the same port. `GET /status` returns the radio settings,
+//! the send rate and the telemetry as JSON, which the page shows under the joysticks.
+//!
+//! The page sends the state as a JSON object with the fields of `ControllerState` about 20
+//! times a second, the missing ones being neutral.
+
+use crate::controller::{ControllerState, Input, InputError, MAX_TRIGGER};
+use crate::dashboard::Stats;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+use tungstenite::Message;
+
+const PAGE: &str = include_str!("../web/index.html");
+
+/// Time after which a browser that stopped sending is considered gone, and the state neutral.
+///
+/// Phones going to sleep don't always close the connection.
+const STALE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Time a client has to send its request, or a connected browser its next state.
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest HTTP request head accepted.
+const MAX_REQUEST_SIZE: usize = 8192;
+
+/// Time between two measures of the send rate.
+const RATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The latest state of each connected browser, and when it came.
+struct Remote {
+    clients: HashMap<u64, (ControllerState, Instant)>,
+    next_client: u64,
+}
+
+struct Session {
+    stats: Stats,
+    send_rate: f64,
+    rate_since: Instant,
+    rate_frames: u64,
+}
+
+struct Shared {
+    remote: Mutex<Remote>,
+    session: Mutex<Session>,
+}
+
+/// The input of the browser that sent the latest state.
+pub struct WebInput {
+    state: ControllerState,
+    shared: Arc<Shared>,
+}
+
+/// Where the control loop publishes the session for `GET /status`.
+#[derive(Clone)]
+pub struct StatusBoard {
+    shared: Arc<Shared>,
+}
+
+impl StatusBoard {
+    pub fn publish(&self, stats: &Stats) {
+        let mut session = self.shared.session.lock().unwrap();
+        let elapsed = session.rate_since.elapsed();
+        if elapsed >= RATE_INTERVAL {
+            session.send_rate = (stats.frames - session.rate_frames) as f64 / elapsed.as_secs_f64();
+            session.rate_since = Instant::now();
+            session.rate_frames = stats.frames;
+        }
+        session.stats = *stats;
+    }
+}
+
+impl WebInput {
+    pub fn status_board(&self) -> StatusBoard {
+        StatusBoard {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl Input for WebInput {
+    /// Takes the state of the browser that sent last, or a neutral one when they all stopped
+    /// sending.
+    fn update_state(&mut self) -> Result<(), InputError> {
+        let remote = self.shared.remote.lock().unwrap();
+        self.state = remote
+            .clients
+            .values()
+            .filter(|(_, updated)| updated.elapsed() < STALE_TIMEOUT)
+            .max_by_key(|(_, updated)| *updated)
+            .map_or_else(ControllerState::new, |(state, _)| *state);
+        Ok(())
+    }
+
+    fn get_state(&self) -> &ControllerState {
+        &self.state
+    }
+}
+
+/// Serves the page on `address`, one thread per connection.
+pub fn init(address: impl ToSocketAddrs) -> Result<WebInput, InputError> {
+    let listener = TcpListener::bind(address).map_err(InputError::Web)?;
+    let local_address = listener.local_addr().map_err(InputError::Web)?;
+    let shared = Arc::new(Shared {
+        remote: Mutex::new(Remote {
+            clients: HashMap::new(),
+            next_client: 0,
+        }),
+        session: Mutex::new(Session {
+            stats: Stats::new(),
+            send_rate: 0.0,
+            rate_since: Instant::now(),
+            rate_frames: 0,
+        }),
+    });
+
+    thread::spawn({
+        let shared = Arc::clone(&shared);
+        move || {
+            for stream in listener.incoming().flatten() {
+                let shared = Arc::clone(&shared);
+                thread::spawn(move || {
+                    if let Err(e) = handle(stream, &shared) {
+                        println!("Web client dropped: {}", e);
+                    }
+                });
+            }
+        }
+    });
+
+    println!("Serving the gamepad page on http://{}", local_address);
+
+    Ok(WebInput {
+        state: ControllerState::new(),
+        shared,
+    })
+}
+
+fn handle(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let (head, head_length) = peek_head(&stream)?;
+
+    if is_websocket(&head) {
+        return stream_states(stream, shared);
+    }
+
+    // The request is read before answering, closing with unread data would reset the connection
+    let mut stream = stream;
+    stream.read_exact(&mut vec![0; head_length])?;
+
+    let mut request_line = head.split_whitespace();
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/")) => ("200 OK", "text/html; charset=utf-8", PAGE.to_string()),
+        (Some("GET"), Some("/status")) => {
+            let session = shared.session.lock().unwrap();
+            (
+                "200 OK",
+                "application/json",
+                status_json(&session).to_string(),
+            )
+        }
+        _ => ("404 Not Found", "text/plain", String::from("Not found\n")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+/// The request head and its length in bytes, left in the socket for the WebSocket handshake.
+fn peek_head(stream: &TcpStream) -> io::Result<(String, usize)> {
+    let deadline = Instant::now() + READ_TIMEOUT;
+    let mut buffer = vec![0; MAX_REQUEST_SIZE];
+
+    loop {
+        let length = stream.peek(&mut buffer)?;
+        if length == 0 {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "no request"));
+        }
+        if let Some(end) = buffer[..length].windows(4).position(|w| w == b"\r\n\r\n") {
+            let head = &buffer[..end + 4];
+            return Ok((String::from_utf8_lossy(head).into_owned(), head.len()));
+        }
+        if length == buffer.len() || Instant::now() > deadline {
+            return Err(io::Error::new(ErrorKind::InvalidData, "incomplete request"));
+        }
+        // Peeking returns at once while the rest of the head is on its way
+        sleep(Duration::from_millis(5));
+    }
+}
+
+fn is_websocket(head: &str) -> bool {
+    head.lines().any(|line| {
+        line.split_once(':').is_some_and(|(name, value)| {
+            name.trim().eq_ignore_ascii_case("upgrade")
+                && value.trim().eq_ignore_ascii_case("websocket")
+        })
+    })
+}
+
+/// Takes the states a browser sends until it goes away.
+fn stream_states(stream: TcpStream, shared: &Shared) -> io::Result<()> {
+    let peer = stream.peer_addr()?;
+    let mut socket = tungstenite::accept(stream)
+        .map_err(|e| io::Error::new(ErrorKind::InvalidData, e.to_string()))?;
+    println!("Browser gamepad connected from {}", peer);
+
+    let client = {
+        let mut remote = shared.remote.lock().unwrap();
+        remote.next_client += 1;
+        remote.next_client
+    };
+
+    let reason = loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match parse_state(&text) {
+                Some(state) => {
+                    let mut remote = shared.remote.lock().unwrap();
+                    remote.clients.insert(client, (state, Instant::now()));
+                }
+                None => println!("Invalid state from {}: {}", peer, text),
+            },
+            Ok(Message::Close(_)) => break String::from("closed"),
+            Ok(_) => {}
+            Err(e) => break e.to_string(),
+        }
+    };
+
+    // Stop at once instead of waiting for the state to go stale, unless another browser drives
+    shared.remote.lock().unwrap().clients.remove(&client);
+    println!("Browser gamepad from {} disconnected: {}", peer, reason);
+    Ok(())
+}
+
+fn parse_state(text: &str) -> Option<ControllerState> {
+    let mut state: ControllerState = serde_json::from_str(text).ok()?;
+    state.l2 = state.l2.min(MAX_TRIGGER);
+    state.r2 = state.r2.min(MAX_TRIGGER);
+    Some(state)
+}
+
+fn status_json(session: &Session) -> Value {
+    let stats = &session.stats;
+    json!({
+        "radio": stats.radio,
+        "frames": stats.frames,
+        "acked": stats.acked,
+        "lost": stats.lost,
+        "send_rate": session.send_rate,
+        "link_up": stats.link_up,
+        "loss": stats.loss,
+        "telemetry": stats.telemetry,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dashboard::RadioSettings;
+    use crate::rf24::{DataRate, Delivery, PowerLevel};
+    use std::net::TcpStream;
+
+    fn get(address: std::net::SocketAddr, path: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: robotoy\r\n\r\n", path).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn streams_states_and_serves_the_status() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        let mut input = init(address).unwrap();
+
+        let mut stats = Stats::new();
+        stats.record_delivery(&Delivery::Lost);
+        input.status_board().publish(&stats);
+
+        assert!(get(address, "/").contains("<html"));
+        let status = get(address, "/status");
+        let body = status.split("\r\n\r\n").nth(1).unwrap();
+        let status: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(status["lost"], 1);
+        assert_eq!(status["radio"], Value::Null);
+        assert!(get(address, "/nothing").starts_with("HTTP/1.1 404"));
+
+        stats.radio = Some(RadioSettings {
+            channel: 40,
+            data_rate: DataRate::_250Kbps,
+            power_level: PowerLevel::_0dBm,
+        });
+        input.status_board().publish(&stats);
+        let status = get(address, "/status");
+        let body = status.split("\r\n\r\n").nth(1).unwrap();
+        let status: Value = serde_json::from_str(body).unwrap();
+        assert_eq!(
+            status["radio"],
+            json!({"channel": 40, "data_rate": "250Kbps", "power_level": "0dBm"})
+        );
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}/", address)).unwrap();
+        socket
+            .send(Message::text(
+                r#"{"x": true, "throttle": -20000, "l2": 65535}"#,
+            ))
+            .unwrap();
+        socket.flush().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while input.get_state().throttle == 0 && Instant::now() < deadline {
+            input.update_state().unwrap();
+        }
+        let state = *input.get_state();
+        assert!(state.x);
+        assert_eq!(state.throttle, -20_000);
+        assert_eq!(state.l2, MAX_TRIGGER);
+
+        socket.close(None).unwrap();
+        while !input.get_state().is_neutral() && Instant::now() < deadline {
+            let _ = socket.read();
+            input.update_state().unwrap();
+        }
+        assert!(input.get_state().is_neutral());
+    }
+
+    #[test]
+    fn keeps_driving_when_another_browser_leaves() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        drop(listener);
+        let mut input = init(address).unwrap();
+        let clients = || input.shared.remote.lock().unwrap().clients.len();
+        let deadline = Instant::now() + Duration::from_secs(2);
+
+        let (mut driving, _) = tungstenite::connect(format!("ws://{}/", address)).unwrap();
+        driving
+            .send(Message::text(r#"{"throttle": -20000}"#))
+            .unwrap();
+        let (mut watching, _) = tungstenite::connect(format!("ws://{}/", address)).unwrap();
+        watching.send(Message::text("{}")).unwrap();
+        while clients() < 2 && Instant::now() < deadline {
+            sleep(Duration::from_millis(1));
+        }
+
+        watching.close(None).unwrap();
+        while clients() > 1 && Instant::now() < deadline {
+            let _ = watching.read();
+        }
+        assert_eq!(clients(), 1);
+
+        input.update_state().unwrap();
+        assert_eq!(input.get_state().throttle, -20_000);
+    }
+}
@@ -0,0 +1,89 @@
+//! Drives the mock radio with scripted input, the way the controller does, and checks the frames
+//! that go out.
+
+use rpi_controller::channel::{switch_frame, CONTROL_MARKER};
+use rpi_controller::controller::Input;
+use rpi_controller::controller::InputError;
+use rpi_controller::error::AppError;
+use rpi_controller::rf24::mock::{configured_radio, MockBus};
+use rpi_controller::rf24::Delivery;
+use rpi_controller::script::{Script, ScriptedInput};
+use rpi_controller::transport::Transport;
+use std::time::Duration;
+
+/// Forward at half speed, a left turn while honking, then a stop.
+const MANEUVER: &str = "
+    0.0 axis righttrigger 16384
+    1.0 axis leftx -32768
+    1.0 button a down
+    1.5 button a up
+    2.0 axis leftx 0
+    2.0 axis righttrigger 0
+    3.0 end
+";
+
+/// Sends the state of `input` at each of `times`, in milliseconds from the start of the script.
+fn drive(
+    transport: &mut dyn Transport,
+    input: &mut ScriptedInput,
+    times: &[u64],
+) -> Result<Vec<Delivery>, AppError> {
+    let mut deliveries = Vec::new();
+    for &millis in times {
+        input.update_at(Duration::from_millis(millis))?;
+        deliveries.push(transport.send(&input.get_state().to_bytes())?);
+    }
+    Ok(deliveries)
+}
+
+#[test]
+fn sends_the_frames_of_a_maneuver() {
+    let bus = MockBus::new();
+    let mut radio = configured_radio(&bus);
+    let mut input = ScriptedInput::new(Script::parse(MANEUVER).unwrap());
+
+    let deliveries = drive(&mut radio, &mut input, &[0, 1000, 1500, 2000]).unwrap();
+
+    assert!(!deliveries.contains(&Delivery::Lost));
+    assert_eq!(
+        bus.transmitted(),
+        vec![
+            vec![0x00, 0, 0, 0x40, 0x00, 0, 0, 0, 0, 0, 0, 0, 0],
+            vec![0x01, 0, 0, 0x40, 0x00, 0x80, 0x00, 0, 0, 0, 0, 0, 0],
+            vec![0x00, 0, 0, 0x40, 0x00, 0x80, 0x00, 0, 0, 0, 0, 0, 0],
+            vec![0x00, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        ]
+    );
+}
+
+#[test]
+fn stops_at_the_end_of_the_script() {
+    let bus = MockBus::new();
+    let mut radio = configured_radio(&bus);
+    let mut input = ScriptedInput::new(Script::parse(MANEUVER).unwrap());
+
+    let result = drive(&mut radio, &mut input, &[0, 3000]);
+
+    assert!(matches!(
+        result,
+        Err(AppError::Input(InputError::ScriptFinished))
+    ));
+    assert_eq!(bus.transmitted().len(), 1);
+}
+
+#[test]
+fn never_sends_the_control_marker_from_the_gamepad() {
+    let bus = MockBus::new();
+    let mut radio = configured_radio(&bus);
+    let mut input = ScriptedInput::new(Script::parse("0.0 axis lefttrigger 32767").unwrap());
+    input.update_at(Duration::ZERO).unwrap();
+    let mut state = *input.get_state();
+    state.l2 = u16::MAX;
+
+    radio.send(&state.to_bytes()).unwrap();
+    radio.send(&switch_frame(40)).unwrap();
+
+    let transmitted = bus.transmitted();
+    assert_ne!(transmitted[0][1..3], CONTROL_MARKER);
+    assert_eq!(transmitted[1][1..3], CONTROL_MARKER);
+}
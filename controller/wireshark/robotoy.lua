@@ -0,0 +1,78 @@
+-- Wireshark dissector for the Robotoy radio payloads.
+-- Generated from src/protocol.rs by `rpi-controller dissector`, don't edit it.
+
+local robotoy = Proto("robotoy", "Robotoy radio payload")
+
+local channel = ProtoField.uint8("robotoy.channel", "Channel", base.DEC)
+local state_buttons = ProtoField.uint8("robotoy.state.buttons", "buttons", base.HEX)
+local state_buttons_x = ProtoField.bool("robotoy.state.buttons.x", "x", 8, nil, 0x01)
+local state_buttons_circle = ProtoField.bool("robotoy.state.buttons.circle", "circle", 8, nil, 0x02)
+local state_buttons_square = ProtoField.bool("robotoy.state.buttons.square", "square", 8, nil, 0x04)
+local state_buttons_triangle = ProtoField.bool("robotoy.state.buttons.triangle", "triangle", 8, nil, 0x08)
+local state_buttons_select = ProtoField.bool("robotoy.state.buttons.select", "select", 8, nil, 0x10)
+local state_buttons_start = ProtoField.bool("robotoy.state.buttons.start", "start", 8, nil, 0x20)
+local state_buttons_l1 = ProtoField.bool("robotoy.state.buttons.l1", "l1", 8, nil, 0x40)
+local state_buttons_r1 = ProtoField.bool("robotoy.state.buttons.r1", "r1", 8, nil, 0x80)
+local state_l2 = ProtoField.uint16("robotoy.state.l2", "l2", base.DEC)
+local state_r2 = ProtoField.uint16("robotoy.state.r2", "r2", base.DEC)
+local state_yaw = ProtoField.int16("robotoy.state.yaw", "yaw", base.DEC)
+local state_throttle = ProtoField.int16("robotoy.state.throttle", "throttle", base.DEC)
+local state_pitch = ProtoField.int16("robotoy.state.pitch", "pitch", base.DEC)
+local state_roll = ProtoField.int16("robotoy.state.roll", "roll", base.DEC)
+local control_marker = ProtoField.uint16("robotoy.control.marker", "marker", base.DEC)
+local control_message = ProtoField.uint8("robotoy.control.message", "message", base.DEC)
+local control_channel = ProtoField.uint8("robotoy.control.channel", "channel", base.DEC)
+local telemetry_mode = ProtoField.uint8("robotoy.telemetry.mode", "mode", base.DEC)
+local telemetry_voltage = ProtoField.uint16("robotoy.telemetry.voltage", "voltage", base.DEC)
+local telemetry_current = ProtoField.int16("robotoy.telemetry.current", "current", base.DEC)
+
+robotoy.fields = { channel, state_buttons, state_buttons_x, state_buttons_circle, state_buttons_square, state_buttons_triangle, state_buttons_select, state_buttons_start, state_buttons_l1, state_buttons_r1, state_l2, state_r2, state_yaw, state_throttle, state_pitch, state_roll, control_marker, control_message, control_channel, telemetry_mode, telemetry_voltage, telemetry_current }
+
+function robotoy.dissector(buffer, pinfo, tree)
+    pinfo.cols.protocol = "Robotoy"
+    local root = tree:add(robotoy, buffer())
+    root:add(channel, buffer(0, 1))
+    local payload = buffer(1):tvb()
+    local length = payload:len()
+    local kind
+    if length == 13 and payload(1, 2):uint() == 0xFFFF then
+        kind = "control"
+    elseif length == 13 then
+        kind = "state"
+    elseif length == 5 then
+        kind = "telemetry"
+    else
+        pinfo.cols.info = "unknown"
+        return
+    end
+    pinfo.cols.info = kind
+    if kind == "state" then
+        local flags = root:add(state_buttons, payload(0, 1))
+        flags:add(state_buttons_x, payload(0, 1))
+        flags:add(state_buttons_circle, payload(0, 1))
+        flags:add(state_buttons_square, payload(0, 1))
+        flags:add(state_buttons_triangle, payload(0, 1))
+        flags:add(state_buttons_select, payload(0, 1))
+        flags:add(state_buttons_start, payload(0, 1))
+        flags:add(state_buttons_l1, payload(0, 1))
+        flags:add(state_buttons_r1, payload(0, 1))
+        root:add(state_l2, payload(1, 2))
+        root:add(state_r2, payload(3, 2))
+        root:add(state_yaw, payload(5, 2))
+        root:add(state_throttle, payload(7, 2))
+        root:add(state_pitch, payload(9, 2))
+        root:add(state_roll, payload(11, 2))
+    end
+    if kind == "control" then
+        root:add(control_marker, payload(1, 2))
+        root:add(control_message, payload(3, 1))
+        root:add(control_channel, payload(4, 1))
+    end
+    if kind == "telemetry" then
+        root:add(telemetry_mode, payload(0, 1))
+        root:add(telemetry_voltage, payload(1, 2))
+        root:add(telemetry_current, payload(3, 2))
+    end
+end
+
+DissectorTable.get("wtap_encap"):add(wtap.USER0, robotoy)
@@ -0,0 +1,210 @@
+#include <Arduino.h>
+#include <SPI.h>
+#include <RF24.h>
+
+/*
+  Sends the payloads the controller writes on the serial port with the radio, and answers each
+  one with its delivery. The framing is described in the README.
+*/
+
+#define RADIO_CE 7
+#define RADIO_CSN 8
+#define RADIO_CHANNEL 125
+#define RADIO_PAYLOAD_SIZE 13
+#define RADIO_MAX_PAYLOAD_SIZE 32
+
+// Waits for the ACKs of the robot, and sends its telemetry back. Must match RADIO_TELEMETRY
+// on the robot.
+#define DONGLE_TELEMETRY 0
+
+// Must match --baud on the controller.
+#define SERIAL_BAUD 115200
+
+#define SYNC 0xA5
+// Longest sequence number and body the controller sends: a full payload.
+#define MAX_REQUEST_LENGTH (1 + RADIO_MAX_PAYLOAD_SIZE)
+// Longest body of an answer: an ACKed delivery with a full ACK payload.
+#define MAX_ANSWER_BODY (2 + RADIO_MAX_PAYLOAD_SIZE)
+#define BUFFER_SIZE 64
+
+#define UNACKNOWLEDGED 0x00
+#define ACKED 0x01
+#define LOST 0x02
+
+uint8_t address[6] = "aaaaa";
+RF24 rf24(RADIO_CE, RADIO_CSN);
+uint8_t received[BUFFER_SIZE];
+uint8_t receivedLength = 0;
+
+/*
+  CRC-8 with the polynomial 0x07, as computed by the controller.
+*/
+uint8_t crc8(const uint8_t *bytes, uint8_t length)
+{
+  uint8_t crc = 0;
+  for (uint8_t i = 0; i < length; i++)
+  {
+    crc ^= bytes[i];
+    for (uint8_t bit = 0; bit < 8; bit++)
+    {
+      crc = crc & 0x80 ? (crc << 1) ^ 0x07 : crc << 1;
+    }
+  }
+  return crc;
+}
+
+void writeFrame(uint8_t sequence, const uint8_t *body, uint8_t length)
+{
+  uint8_t frame[MAX_ANSWER_BODY + 4];
+  frame[0] = SYNC;
+  frame[1] = length + 1;
+  frame[2] = sequence;
+  memcpy(frame + 3, body, length);
+  frame[length + 3] = crc8(frame + 1, length + 2);
+  Serial.write(frame, length + 4);
+}
+
+void dropReceived(uint8_t count)
+{
+  memmove(received, received + count, receivedLength - count);
+  receivedLength -= count;
+}
+
+/*
+  Takes the first valid frame out of the received bytes, returning the length of its body, or
+  -1 when there is none yet.
+
+  A sync byte whose frame doesn't check out is skipped, and so is one still waiting for its
+  bytes when a complete frame follows it, like the controller does.
+*/
+int decodeFrame(uint8_t *sequence, uint8_t *body)
+{
+  int incomplete = -1;
+
+  for (uint8_t start = 0; start < receivedLength; start++)
+  {
+    if (received[start] != SYNC)
+    {
+      continue;
+    }
+    if (start + 1 >= receivedLength)
+    {
+      if (incomplete < 0)
+      {
+        incomplete = start;
+      }
+      continue;
+    }
+
+    uint8_t length = received[start + 1];
+    if (length == 0 || length > MAX_REQUEST_LENGTH)
+    {
+      continue;
+    }
+    if (start + length + 3 > receivedLength)
+    {
+      if (incomplete < 0)
+      {
+        incomplete = start;
+      }
+      continue;
+    }
+    if (crc8(received + start + 1, length + 1) != received[start + length + 2])
+    {
+      continue;
+    }
+
+    *sequence = received[start + 2];
+    memcpy(body, received + start + 3, length - 1);
+    dropReceived(start + length + 3);
+    return length - 1;
+  }
+
+  dropReceived(incomplete < 0 ? receivedLength : incomplete);
+  return -1;
+}
+
+/*
+  Sends a payload and answers with its delivery, encoded as for the network gateway.
+*/
+void send(uint8_t sequence, const uint8_t *payload, uint8_t length)
+{
+  uint8_t answer[MAX_ANSWER_BODY];
+
+#if DONGLE_TELEMETRY
+  if (!rf24.write(payload, length))
+  {
+    answer[0] = LOST;
+    writeFrame(sequence, answer, 1);
+    return;
+  }
+
+  answer[0] = ACKED;
+  answer[1] = rf24.getARC();
+  uint8_t size = 0;
+  if (rf24.available())
+  {
+    // A corrupt ACK payload reads as 0, and is flushed
+    size = rf24.getDynamicPayloadSize();
+    rf24.read(answer + 2, size);
+  }
+  writeFrame(sequence, answer, 2 + size);
+#else
+  rf24.write(payload, length);
+  answer[0] = UNACKNOWLEDGED;
+  writeFrame(sequence, answer, 1);
+#endif
+}
+
+void setup()
+{
+  Serial.begin(SERIAL_BAUD);
+  pinMode(LED_BUILTIN, OUTPUT);
+
+  // Without a radio nothing is answered, and the controller counts the frames as lost
+  while (!rf24.begin())
+  {
+    digitalWrite(LED_BUILTIN, HIGH);
+    delay(100);
+  }
+  digitalWrite(LED_BUILTIN, LOW);
+
+  rf24.setPALevel(RF24_PA_MAX);
+  rf24.setDataRate(RF24_250KBPS);
+  rf24.setPayloadSize(RADIO_PAYLOAD_SIZE);
+#if DONGLE_TELEMETRY
+  rf24.setAutoAck(true);
+  rf24.enableDynamicPayloads();
+  rf24.enableAckPayload();
+  // 500us between 3 retries, like the controller with --telemetry
+  rf24.setRetries(1, 3);
+#else
+  rf24.setAutoAck(false);
+#endif
+  rf24.setChannel(RADIO_CHANNEL);
+  rf24.openWritingPipe(address);
+  rf24.stopListening();
+}
+
+void loop()
+{
+  while (Serial.available() > 0 && receivedLength < BUFFER_SIZE)
+  {
+    received[receivedLength++] = Serial.read();
+  }
+
+  uint8_t sequence;
+  uint8_t payload[RADIO_MAX_PAYLOAD_SIZE];
+  int length;
+  while ((length = decodeFrame(&sequence, payload)) >= 0)
+  {
+    // Nothing to send, and no answer: the controller counts it as lost
+    if (length == 0)
+    {
+      continue;
+    }
+    digitalWrite(LED_BUILTIN, HIGH);
+    send(sequence, payload, length);
+    digitalWrite(LED_BUILTIN, LOW);
+  }
+}
@@ -3,11 +3,13 @@
 Radio::Radio(int ce_pin, int csn_pin, int channel, uint8_t *address)
 {
   m_channel = channel;
+  m_homeChannel = channel;
   m_connected = false;
   m_initialized = false;
   m_connectionSpeedRate = 0;
   m_packageCounter = 0;
   m_timeout = new Timer(RADIO_TIMEOUT);
+  m_homeTimeout = new Timer(RADIO_HOME_TIMEOUT);
   m_packageConnectionSpeedTimer = new Timer(RADIO_CONNECTION_SPEED_MEASURE_INTERVAL);
   memcpy(m_address, address, 6);
   m_rf24 = new RF24(ce_pin, csn_pin);
@@ -17,6 +19,7 @@ Radio::~Radio()
 {
   delete m_rf24;
   delete m_timeout;
+  delete m_homeTimeout;
 }
 
 void Radio::initialize()
@@ -31,7 +34,13 @@ void Radio::initialize()
     m_rf24->setPALevel(RF24_PA_MAX);
     m_rf24->setDataRate(RF24_250KBPS);
     m_rf24->setPayloadSize(RADIO_PAYLOAD_SIZE);
+#if RADIO_TELEMETRY
+    m_rf24->setAutoAck(true);
+    m_rf24->enableDynamicPayloads();
+    m_rf24->enableAckPayload();
+#else
     m_rf24->setAutoAck(false);
+#endif
     m_rf24->setChannel(m_channel);
     m_rf24->openReadingPipe(0, m_address);
     m_rf24->startListening();
@@ -39,6 +48,7 @@ void Radio::initialize()
   }
 
   m_timeout->start();
+  m_homeTimeout->start();
   m_packageConnectionSpeedTimer->start();
 }
 
@@ -55,6 +65,7 @@ bool Radio::available()
   if (available_data)
   {
     m_timeout->reset();
+    m_homeTimeout->reset();
     m_connected = true;
     m_packageCounter++;
     calculateConnectionSpeed();
@@ -66,6 +77,12 @@ bool Radio::available()
     m_packageCounter = 0;
   }
 
+  // The controller lost us after a channel switch, it looks for us on the home channel.
+  if (!available_data && m_channel != m_homeChannel && m_homeTimeout->expired(false))
+  {
+    setChannel(m_homeChannel);
+  }
+
   return available_data;
 }
 
@@ -75,6 +92,31 @@ void Radio::read(void *payload)
   m_rf24->read(payload, bytes);
 }
 
+/*
+  Queues the telemetry to be sent with the ACK of the next received packet.
+*/
+void Radio::writeTelemetry(const void *telemetry, uint8_t size)
+{
+#if RADIO_TELEMETRY
+  m_rf24->writeAckPayload(0, telemetry, size);
+#endif
+}
+
+/*
+  Moves to another channel, asked by the controller with a control payload.
+*/
+void Radio::setChannel(uint8_t channel)
+{
+  m_channel = channel;
+  m_rf24->setChannel(channel);
+  m_homeTimeout->reset();
+}
+
+uint8_t Radio::getChannel()
+{
+  return m_channel;
+}
+
 bool Radio::isConnected()
 {
   return isInitialized() && m_connected;
@@ -10,17 +10,31 @@
 #define RADIO_TIMEOUT 1000
 #define RADIO_CONNECTION_SPEED_MEASURE_INTERVAL 1000
 
+// Time without payloads on another channel before going back to the one the radio started on.
+// Matches HOME_TIMEOUT on the controller.
+#define RADIO_HOME_TIMEOUT 3000
+
+// Control payloads have 0xFFFF in the L2 field, which the trigger never reaches.
+#define RADIO_CONTROL_MARKER 0xFF
+#define RADIO_CONTROL_SWITCH_CHANNEL 0x01
+
+// Sends telemetry back inside the ACK packets. The controller must run with --telemetry.
+#define RADIO_TELEMETRY 0
+#define RADIO_TELEMETRY_SIZE 5
+
 class Radio
 {
 private:
   RF24 *m_rf24;
   uint8_t m_address[6];
   uint8_t m_channel;
+  uint8_t m_homeChannel;
   bool m_connected;
   bool m_initialized;
   int m_connectionSpeedRate;
   int m_packageCounter;
   Timer *m_timeout;
+  Timer *m_homeTimeout;
   Timer *m_packageConnectionSpeedTimer;
   void calculateConnectionSpeed();
 
@@ -30,6 +44,9 @@ public:
   void initialize();
   bool available();
   void read(void *payload);
+  void writeTelemetry(const void *telemetry, uint8_t size);
+  void setChannel(uint8_t channel);
+  uint8_t getChannel();
   bool isConnected();
   bool isInitialized();
   int getConnectionSpeedRate();
@@ -59,6 +59,11 @@ bool State::isDisconnected()
   return m_state == STATE_DISCONNECTED;
 }
 
+uint8_t State::getState()
+{
+  return m_state;
+}
+
 bool State::bounced()
 {
   return millis() - m_lastStateTime > STATE_BOUNCE_DELAY;
@@ -24,6 +24,7 @@ public:
   bool isRunning();
   bool isDisconnected();
   bool isSetup();
+  uint8_t getState();
   bool bounced();
 };
 
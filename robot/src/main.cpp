@@ -204,6 +204,45 @@ void execute_state()
   }
 }
 
+/*
+  Telemetry layout, big endian:
+    0: state
+    1-2: voltage in millivolts
+    3-4: current in milliamps
+*/
+void send_telemetry()
+{
+  uint16_t voltage = voltimeter.getVoltage() * 1000;
+  int16_t current = voltimeter.getCurrent() * 1000;
+  uint8_t telemetry[RADIO_TELEMETRY_SIZE] = {
+      state.getState(),
+      (uint8_t)(voltage >> 8),
+      (uint8_t)voltage,
+      (uint8_t)(current >> 8),
+      (uint8_t)current};
+
+  radio.writeTelemetry(telemetry, RADIO_TELEMETRY_SIZE);
+}
+
+/*
+  Control payload layout:
+    1-2: 0xFFFF, where L2 is in a controller state
+    3: message, 0x01 to switch channel
+    4: new channel
+*/
+bool is_control_payload(uint8_t *payload)
+{
+  return payload[1] == RADIO_CONTROL_MARKER && payload[2] == RADIO_CONTROL_MARKER;
+}
+
+void handle_control_payload(uint8_t *payload)
+{
+  if (payload[3] == RADIO_CONTROL_SWITCH_CHANNEL && payload[4] <= 125)
+  {
+    radio.setChannel(payload[4]);
+  }
+}
+
 void set_state_from_connection(bool connected)
 {
   if (connected)
@@ -242,7 +281,15 @@ void loop()
   if (radio.isInitialized() && radio.available())
   {
     radio.read(&payload);
-    controller.load_state_from_payload(payload);
+    if (is_control_payload(payload))
+    {
+      handle_control_payload(payload);
+    }
+    else
+    {
+      controller.load_state_from_payload(payload);
+    }
+    send_telemetry();
   }
 
   set_state_from_connection(radio.isConnected());